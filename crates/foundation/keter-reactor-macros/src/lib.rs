@@ -0,0 +1,269 @@
+// MIT/Apache2 License
+
+//! Procedural macros for `keter-reactor`.
+//!
+//! These are re-exported from `keter-reactor` and should be used from there.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Error, FnArg, ItemFn, Pat, ReturnType, Signature};
+
+/// Mark a function as the entry point of the program.
+///
+/// This expands to the `keter_reactor::main!` macro, which generates a `main` function on
+/// desktop platforms and an `android_main` function on Android. Both of them run the
+/// function on a new `Reactor`.
+///
+/// The function can be an `async fn main()`, optionally returning `keter_reactor::Result<()>`.
+/// When it completes, the reactor exits. If it returns an error, the error is printed and the
+/// process exits with a non-zero exit code.
+///
+/// ```ignore
+/// #[keter_reactor::attr::main]
+/// async fn main() -> keter_reactor::Result<()> {
+///     keter_reactor::Timer::after(std::time::Duration::from_secs(1)).await;
+///     Ok(())
+/// }
+/// ```
+///
+/// The function can also take the `Reactor` and drive it manually.
+///
+/// ```ignore
+/// #[keter_reactor::attr::main]
+/// fn main(reactor: keter_reactor::Reactor) -> keter_reactor::Main {
+///     reactor.block_on(keter_reactor::exit())
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+    expand_main(args.into(), item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Mark an `async fn` as a test that runs on a new `Reactor`.
+///
+/// The test can optionally return `keter_reactor::Result<()>`; returning an error fails the
+/// test. The reactor is allowed to run on any thread, so this is only available on platforms
+/// where the `Reactor` can be instantiated freely.
+///
+/// ```ignore
+/// #[keter_reactor::test]
+/// async fn timer_fires() {
+///     keter_reactor::Timer::after(std::time::Duration::from_millis(10)).await;
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+    expand_test(args.into(), item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Expand the `main` attribute.
+fn expand_main(args: TokenStream2, item: ItemFn) -> syn::Result<TokenStream2> {
+    reject_args(args, "main")?;
+    check_signature(&item.sig)?;
+
+    let ItemFn {
+        attrs, sig, block, ..
+    } = item;
+
+    if sig.ident != "main" {
+        return Err(Error::new(
+            sig.ident.span(),
+            "the `main` attribute can only be used on a function named `main`",
+        ));
+    }
+
+    if sig.asyncness.is_none() {
+        // This is the form that takes a reactor and drives it manually.
+        let (arg, arg_ty) = match reactor_arg(&sig)? {
+            Some(arg) => arg,
+            None => {
+                return Err(Error::new(
+                    sig.fn_token.span,
+                    "expected `async fn main()` or `fn main(reactor: Reactor) -> Main`",
+                ))
+            }
+        };
+
+        let ret_ty = match &sig.output {
+            ReturnType::Type(_, ty) => ty,
+            ReturnType::Default => {
+                return Err(Error::new(
+                    sig.paren_token.span.close(),
+                    "expected a return type of `keter_reactor::Main`",
+                ))
+            }
+        };
+
+        return Ok(quote! {
+            ::keter_reactor::main! {
+                #(#attrs)*
+                fn main(#arg: #arg_ty) -> #ret_ty #block
+            }
+        });
+    }
+
+    if !sig.inputs.is_empty() {
+        return Err(Error::new(
+            sig.inputs.span(),
+            "`async fn main` does not take any arguments",
+        ));
+    }
+
+    let body = async_body(&sig, &block);
+    Ok(quote! {
+        ::keter_reactor::main! {
+            #(#attrs)*
+            fn main(__reactor: ::keter_reactor::Reactor) -> ::keter_reactor::Main {
+                __reactor.__block_on_result(#body)
+            }
+        }
+    })
+}
+
+/// Expand the `test` attribute.
+fn expand_test(args: TokenStream2, item: ItemFn) -> syn::Result<TokenStream2> {
+    reject_args(args, "test")?;
+    check_signature(&item.sig)?;
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = item;
+
+    if sig.asyncness.is_none() {
+        return Err(Error::new(
+            sig.fn_token.span,
+            "the `test` attribute can only be used on an `async fn`",
+        ));
+    }
+
+    if !sig.inputs.is_empty() {
+        return Err(Error::new(
+            sig.inputs.span(),
+            "tests do not take any arguments",
+        ));
+    }
+
+    let name = &sig.ident;
+    let body = async_body(&sig, &block);
+    Ok(quote! {
+        #[::core::prelude::v1::test]
+        #(#attrs)*
+        #vis fn #name() {
+            let reactor = <::keter_reactor::Reactor as ::keter_reactor::platform::any_thread::ReactorExt>::with_any_thread(
+                <::keter_reactor::Reactor as ::keter_reactor::platform::instantiation::ReactorExt>::new()
+            );
+
+            match reactor.__block_on_result(#body) {
+                ::core::result::Result::Ok(finished) => ::core::assert!(
+                    finished.is_success(),
+                    "test exited with code {}",
+                    finished.exit_code()
+                ),
+                ::core::result::Result::Err(err) => ::core::panic!("test failed: {}", err),
+            }
+        }
+    })
+}
+
+/// Reject any arguments passed to the attribute.
+fn reject_args(args: TokenStream2, name: &str) -> syn::Result<()> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new_spanned(
+            args,
+            format!("the `{name}` attribute does not take any arguments"),
+        ))
+    }
+}
+
+/// Reject function signatures that cannot be used as an entry point.
+fn check_signature(sig: &Signature) -> syn::Result<()> {
+    if let Some(constness) = &sig.constness {
+        return Err(Error::new(constness.span, "entry points cannot be `const`"));
+    }
+
+    if let Some(unsafety) = &sig.unsafety {
+        return Err(Error::new(unsafety.span, "entry points cannot be `unsafe`"));
+    }
+
+    if let Some(abi) = &sig.abi {
+        return Err(Error::new(abi.span(), "entry points cannot have an ABI"));
+    }
+
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        return Err(Error::new(
+            sig.generics.span(),
+            "entry points cannot be generic",
+        ));
+    }
+
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new(
+            variadic.span(),
+            "entry points cannot be variadic",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Get the reactor argument of a function that drives the reactor manually.
+fn reactor_arg(sig: &Signature) -> syn::Result<Option<(&syn::Ident, &syn::Type)>> {
+    let mut inputs = sig.inputs.iter();
+    let (arg, None) = (inputs.next(), inputs.next()) else {
+        return Ok(None);
+    };
+
+    match arg {
+        None => Ok(None),
+        Some(FnArg::Receiver(recv)) => {
+            Err(Error::new(recv.span(), "entry points cannot take `self`"))
+        }
+        Some(FnArg::Typed(arg)) => match &*arg.pat {
+            Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => {
+                if let Some(mutability) = &pat.mutability {
+                    return Err(Error::new(
+                        mutability.span,
+                        "the reactor argument cannot be `mut`",
+                    ));
+                }
+
+                Ok(Some((&pat.ident, &arg.ty)))
+            }
+            pat => Err(Error::new(
+                pat.span(),
+                "expected an identifier for the reactor argument",
+            )),
+        },
+    }
+}
+
+/// Wrap the body of an `async fn` into a future returning `keter_reactor::Result<()>`.
+fn async_body(sig: &Signature, block: &syn::Block) -> TokenStream2 {
+    match &sig.output {
+        ReturnType::Default => quote! {
+            async move {
+                let () = async move #block.await;
+                ::keter_reactor::Result::Ok(())
+            }
+        },
+        ReturnType::Type(_, ty) => quote_spanned! {ty.span()=>
+            async move {
+                let result: #ty = async move #block.await;
+                result
+            }
+        },
+    }
+}
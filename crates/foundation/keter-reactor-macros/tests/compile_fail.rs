@@ -0,0 +1,9 @@
+// MIT/Apache2 License
+
+//! Check the errors for entry points with the wrong signature.
+
+#[test]
+fn compile_fail() {
+    let tests = trybuild::TestCases::new();
+    tests.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,4 @@
+#[keter_reactor::attr::main]
+async fn main(count: u32) {
+    let _ = count;
+}
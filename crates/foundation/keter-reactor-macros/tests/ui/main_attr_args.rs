@@ -0,0 +1,2 @@
+#[keter_reactor::attr::main(flavor = "current_thread")]
+async fn main() {}
@@ -0,0 +1,2 @@
+#[keter_reactor::attr::main]
+async fn main<T>() {}
@@ -0,0 +1,4 @@
+#[keter_reactor::attr::main]
+fn main(mut reactor: keter_reactor::Reactor) -> keter_reactor::Main {
+    reactor.block_on(keter_reactor::exit())
+}
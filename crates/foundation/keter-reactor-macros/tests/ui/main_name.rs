@@ -0,0 +1,4 @@
+#[keter_reactor::attr::main]
+async fn start() {}
+
+fn main() {}
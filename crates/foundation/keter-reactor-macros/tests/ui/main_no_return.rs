@@ -0,0 +1,4 @@
+#[keter_reactor::attr::main]
+fn main(reactor: keter_reactor::Reactor) {
+    let _ = reactor;
+}
@@ -0,0 +1,2 @@
+#[keter_reactor::attr::main]
+fn main() {}
@@ -0,0 +1,6 @@
+#[keter_reactor::test]
+async fn takes_args(count: u32) {
+    let _ = count;
+}
+
+fn main() {}
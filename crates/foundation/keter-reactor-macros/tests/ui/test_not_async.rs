@@ -0,0 +1,4 @@
+#[keter_reactor::test]
+fn not_async() {}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[keter_reactor::test]
+async unsafe fn is_unsafe() {}
+
+fn main() {}
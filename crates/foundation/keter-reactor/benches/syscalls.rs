@@ -0,0 +1,186 @@
+// MIT/Apache2 License
+
+//! Compare how many system calls each iteration of the reactor's loop makes.
+//!
+//! Run with `cargo bench --bench syscalls --features io-uring`. Each backend runs a
+//! workload of echo round trips with a thread, each followed by a short timer, in a child
+//! process. The child's main thread is traced with `ptrace` to count its system calls.
+//! Running the workload twice with a different number of rounds cancels out the cost of
+//! starting up and shutting down, leaving the cost of the rounds themselves. Loop
+//! iterations are counted as polls of the reactor's future.
+
+#[cfg(target_os = "linux")]
+fn main() {
+    bench::main();
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    println!("the system call benchmarks only run on Linux");
+}
+
+#[cfg(target_os = "linux")]
+mod bench {
+    use keter_reactor::platform::any_thread::ReactorBuilderExt as _;
+    use keter_reactor::platform::instantiation::ReactorBuilderExt as _;
+    #[cfg(feature = "io-uring")]
+    use keter_reactor::platform::io_uring::ReactorBuilderExt as _;
+    use keter_reactor::platform::poll_io::Async;
+    use keter_reactor::{exit, Instrument, ReactorBuilder, Timer};
+
+    use futures_lite::prelude::*;
+    use nix::sys::ptrace::{self, Event, Options};
+    use nix::sys::wait::{waitpid, WaitStatus};
+    use nix::unistd::Pid;
+
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::process::{Command, Stdio};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// The environment variable that tells the child which workload to run.
+    const CHILD: &str = "KETER_REACTOR_SYSCALLS_CHILD";
+
+    /// The number of rounds in the shorter run.
+    const ROUNDS: usize = 1_000;
+
+    /// Counts how many times the reactor polls its futures.
+    #[derive(Clone, Default)]
+    struct Iterations(Arc<AtomicUsize>);
+
+    impl Instrument for Iterations {
+        fn on_poll(&self, _name: Option<&str>, _busy: Duration) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn main() {
+        if let Ok(workload) = std::env::var(CHILD) {
+            let (backend, rounds) = workload.split_once(':').unwrap();
+            child(backend == "io_uring", rounds.parse().unwrap());
+            return;
+        }
+
+        compare("epoll");
+        #[cfg(feature = "io-uring")]
+        compare("io_uring");
+        #[cfg(not(feature = "io-uring"))]
+        println!("enable the `io-uring` feature to compare against io_uring");
+    }
+
+    /// Measure the system calls per round and per iteration for a backend.
+    fn compare(backend: &str) {
+        let (short_syscalls, short_iterations) = trace(backend, ROUNDS);
+        let (long_syscalls, long_iterations) = trace(backend, ROUNDS * 2);
+
+        let syscalls = long_syscalls.saturating_sub(short_syscalls) as f64;
+        let iterations = long_iterations.saturating_sub(short_iterations) as f64;
+        println!(
+            "{backend:<10} syscalls/round {:>6.2}  iterations/round {:>5.2}  syscalls/iteration {:>5.2}",
+            syscalls / ROUNDS as f64,
+            iterations / ROUNDS as f64,
+            syscalls / iterations.max(1.0),
+        );
+    }
+
+    /// Run the workload in a child process and count the system calls its main thread
+    /// makes, along with the iterations it reports.
+    fn trace(backend: &str, rounds: usize) -> (usize, usize) {
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .env(CHILD, format!("{backend}:{rounds}"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let pid = Pid::from_raw(child.id() as i32);
+
+        // Attach while the child waits for its go-ahead, and stop it at the next system
+        // call.
+        ptrace::seize(
+            pid,
+            Options::PTRACE_O_TRACESYSGOOD
+                | Options::PTRACE_O_TRACEEXIT
+                | Options::PTRACE_O_EXITKILL,
+        )
+        .unwrap();
+        ptrace::interrupt(pid).unwrap();
+        waitpid(pid, None).unwrap();
+        ptrace::syscall(pid, None).unwrap();
+        child.stdin.take().unwrap().write_all(&[1]).unwrap();
+
+        // Every system call stops the child twice: once on entry, and once on exit. Stop
+        // tracing once it starts exiting, so that it can be waited on normally.
+        let mut stops = 0;
+        loop {
+            match waitpid(pid, None).unwrap() {
+                WaitStatus::PtraceSyscall(_) => {
+                    stops += 1;
+                    ptrace::syscall(pid, None).unwrap();
+                }
+                WaitStatus::PtraceEvent(_, _, event)
+                    if event == Event::PTRACE_EVENT_EXIT as i32 =>
+                {
+                    ptrace::detach(pid, None).unwrap();
+                    break;
+                }
+                WaitStatus::Stopped(_, signal) => ptrace::syscall(pid, signal).unwrap(),
+                WaitStatus::Signaled(_, signal, _) => panic!("the workload died from {signal}"),
+                _ => ptrace::syscall(pid, None).unwrap(),
+            }
+        }
+
+        let mut iterations = String::new();
+        child
+            .stdout
+            .take()
+            .unwrap()
+            .read_to_string(&mut iterations)
+            .unwrap();
+        assert!(child.wait().unwrap().success(), "the workload failed");
+        (stops / 2, iterations.trim().parse().unwrap())
+    }
+
+    /// Run the workload once the parent is tracing this process.
+    fn child(io_uring: bool, rounds: usize) {
+        let mut go = [0u8];
+        std::io::stdin().read_exact(&mut go).unwrap();
+
+        let (left, mut right) = UnixStream::pair().unwrap();
+        let peer = std::thread::spawn(move || {
+            let mut buf = [0u8];
+            while right.read(&mut buf).unwrap() == 1 {
+                right.write_all(&buf).unwrap();
+            }
+        });
+
+        let iterations = Iterations::default();
+        let builder = ReactorBuilder::new()
+            .any_thread(true)
+            .instrument(iterations.clone());
+        #[cfg(feature = "io-uring")]
+        let builder = builder.io_uring(io_uring);
+        #[cfg(not(feature = "io-uring"))]
+        assert!(!io_uring);
+
+        builder
+            .build()
+            .block_on(async move {
+                let mut stream = Async::new(left).unwrap();
+                let mut buf = [0u8];
+                for _ in 0..rounds {
+                    stream.write_all(&[1]).await.unwrap();
+                    stream.read_exact(&mut buf).await.unwrap();
+                    Timer::after(Duration::from_micros(50)).await;
+                }
+
+                drop(stream);
+                exit().await
+            })
+            .unwrap();
+        peer.join().unwrap();
+
+        println!("{}", iterations.0.load(Ordering::Relaxed));
+    }
+}
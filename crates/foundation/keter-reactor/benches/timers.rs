@@ -0,0 +1,115 @@
+// MIT/Apache2 License
+
+//! Compare the cost of many concurrent timers.
+//!
+//! Run with `cargo bench --bench timers`. Each run arms 10,000 one-shot timers spread
+//! over one second, and reports how long it took, how much CPU time the process used and
+//! how many times the reactor woke up.
+
+#[cfg(target_os = "linux")]
+fn main() {
+    bench::main();
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    println!("the timer benchmarks only run on Linux");
+}
+
+#[cfg(target_os = "linux")]
+mod bench {
+    use keter_reactor::platform::any_thread::ReactorBuilderExt as _;
+    use keter_reactor::platform::instantiation::ReactorBuilderExt as _;
+    use keter_reactor::{exit, spawn_local, Instrument, ReactorBuilder, Timer};
+
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// The number of timers to arm.
+    const TIMERS: usize = 10_000;
+
+    /// The span of time the deadlines are spread over.
+    const SPREAD: Duration = Duration::from_secs(1);
+
+    /// Counts how many times the reactor polls its futures.
+    #[derive(Clone, Default)]
+    struct Wakeups(Arc<AtomicUsize>);
+
+    impl Instrument for Wakeups {
+        fn on_poll(&self, _name: Option<&str>, _busy: Duration) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn main() {
+        run("async-io timers", Duration::ZERO, |delay| {
+            Box::pin(async move {
+                async_io::Timer::after(delay).await;
+            })
+        });
+        run("timer wheel", Duration::ZERO, |delay| {
+            Box::pin(async move {
+                Timer::after(delay).await;
+            })
+        });
+        run(
+            "timer wheel, 10ms slack",
+            Duration::from_millis(10),
+            |delay| {
+                Box::pin(async move {
+                    Timer::after(delay).await;
+                })
+            },
+        );
+    }
+
+    /// Arm every timer with `timer` and wait for all of them to fire.
+    fn run(name: &str, slack: Duration, timer: fn(Duration) -> Pin<Box<dyn Future<Output = ()>>>) {
+        let wakeups = Wakeups::default();
+        let reactor = ReactorBuilder::new()
+            .any_thread(true)
+            .timer_slack(slack)
+            .instrument(wakeups.clone())
+            .build();
+
+        let start = Instant::now();
+        let cpu_start = cpu_time();
+        reactor
+            .block_on(async move {
+                // Spread the deadlines out with a fixed sequence, so every run is the same.
+                let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+                let handles = (0..TIMERS)
+                    .map(|_| {
+                        seed = seed
+                            .wrapping_mul(6_364_136_223_846_793_005)
+                            .wrapping_add(1_442_695_040_888_963_407);
+                        let delay = SPREAD.mul_f64((seed >> 11) as f64 / (1u64 << 53) as f64);
+                        spawn_local(timer(delay))
+                    })
+                    .collect::<Vec<_>>();
+
+                for handle in handles {
+                    handle.await;
+                }
+
+                exit().await
+            })
+            .unwrap();
+
+        println!(
+            "{name:<24} wall {:>8.2?}  cpu {:>8.2?}  wakeups {:>6}",
+            start.elapsed(),
+            cpu_time() - cpu_start,
+            wakeups.0.load(Ordering::Relaxed)
+        );
+    }
+
+    /// Get the CPU time used by this process so far.
+    fn cpu_time() -> Duration {
+        let time = rustix::time::clock_gettime(rustix::time::ClockId::ProcessCPUTime);
+        Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+    }
+}
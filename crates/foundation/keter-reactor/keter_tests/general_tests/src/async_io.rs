@@ -1,11 +1,19 @@
 // MIT/Apache2 License
 
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use futures_lite::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use futures_lite::stream::StreamExt;
 #[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
 use keter_reactor::platform::poll_io::Async;
 
 use std::io;
 #[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use std::io::{IoSlice, IoSliceMut, Read};
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
 use std::net::{TcpListener, TcpStream};
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use std::os::unix::net::UnixStream;
 
 pub(crate) async fn test() {
     entry().await.unwrap();
@@ -30,10 +38,65 @@ async fn entry() -> io::Result<()> {
         stream1.get_ref().local_addr()?,
     );
 
+    // Read and write over the TCP connection.
+    let (mut stream1, mut stream2) = (stream1, stream2);
+    stream1.write_all(b"hello over tcp").await?;
+    let mut buf = [0u8; 14];
+    stream2.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"hello over tcp");
+
     // Now that the listener is closed, connect should fail.
     let err = Async::<TcpStream>::connect(addr).await.unwrap_err();
     assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
 
+    // Read and write over a pair of Unix sockets.
+    let (mut left, mut right) = Async::<UnixStream>::pair()?;
+    left.write_all(b"ping").await?;
+    let mut buf = [0u8; 4];
+    right.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"ping");
+
+    // Vectored I/O.
+    let written = right
+        .write_vectored(&[IoSlice::new(b"po"), IoSlice::new(b"ng")])
+        .await?;
+    assert_eq!(written, 4);
+    let (mut first, mut second) = ([0u8; 2], [0u8; 2]);
+    let mut read = 0;
+    while read < 4 {
+        let mut bufs = [IoSliceMut::new(&mut first), IoSliceMut::new(&mut second)];
+        let mut bufs = &mut bufs[..];
+        IoSliceMut::advance_slices(&mut bufs, read);
+        read += left.read_vectored(bufs).await?;
+    }
+    assert_eq!((&first, &second), (b"po", b"ng"));
+
+    // I/O through shared references.
+    (&left).write_all(b"shared\nlines\n").await?;
+    let mut lines = BufReader::new(&right).lines();
+    assert_eq!(lines.next().await.transpose()?.as_deref(), Some("shared"));
+    assert_eq!(lines.next().await.transpose()?.as_deref(), Some("lines"));
+    drop(lines);
+
+    // Closure-based helpers.
+    left.write_with(|mut socket| std::io::Write::write(&mut socket, b"with"))
+        .await?;
+    let mut buf = [0u8; 4];
+    let mut read = 0;
+    while read < 4 {
+        read += right
+            .read_with(|mut socket| socket.read(&mut buf[read..]))
+            .await?;
+    }
+    assert_eq!(&buf, b"with");
+
+    // Closing one end shows up as EOF on the other.
+    left.close().await?;
+    drop(left);
+    let mut rest = Vec::new();
+    right.read_to_end(&mut rest).await?;
+    assert!(rest.is_empty());
+
     Ok(())
 }
 
@@ -0,0 +1,77 @@
+// MIT/Apache2 License
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use futures_lite::{future, prelude::*};
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use keter_reactor::platform::poll_io::{Async, ConnectError};
+
+use std::io;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use std::net::{Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+pub(crate) async fn test() {
+    entry().await.unwrap();
+}
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+async fn entry() -> io::Result<()> {
+    // Listen on the IPv4 loopback, and on the IPv6 loopback if it is available.
+    let listener4 = Async::<TcpListener>::bind(([127, 0, 0, 1], 0))?;
+    let port = listener4.get_ref().local_addr()?.port();
+    let listener6 = Async::<TcpListener>::bind((Ipv6Addr::LOCALHOST, port)).ok();
+
+    // Connect to whichever loopback address `localhost` resolves to first.
+    let accept = async {
+        match &listener6 {
+            Some(listener6) => listener4.accept().or(listener6.accept()).await,
+            None => listener4.accept().await,
+        }
+    };
+    let (stream, accepted) = future::zip(
+        Async::<TcpStream>::connect_to_host("localhost", port),
+        accept,
+    )
+    .await;
+    let accepted = accepted.map(|(accepted, _)| accepted);
+    let (stream, accepted) = (stream?, accepted?);
+    assert_eq!(
+        stream.get_ref().local_addr()?,
+        accepted.get_ref().peer_addr()?
+    );
+    drop((stream, accepted));
+
+    // With only an IPv4 listener, we fall back to it even if IPv6 is tried first.
+    drop(listener6);
+    let (stream, accepted) = future::zip(
+        Async::<TcpStream>::connect_to_host("localhost", port),
+        listener4.accept(),
+    )
+    .await;
+    assert!(stream?.get_ref().peer_addr()?.is_ipv4());
+    accepted?;
+
+    // Once nothing is listening, every address reports an error.
+    drop(listener4);
+    let resolved = ("localhost", port)
+        .to_socket_addrs()?
+        .collect::<Vec<SocketAddr>>();
+    let err = Async::<TcpStream>::connect_to_host("localhost", port)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+    let err = err
+        .get_ref()
+        .and_then(|err| err.downcast_ref::<ConnectError>())
+        .unwrap();
+    assert_eq!(err.errors().len(), resolved.len());
+    for (address, _) in err.errors() {
+        assert!(resolved.contains(address));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(all(unix, not(target_vendor = "apple"), not(target_os = "android"))))]
+async fn entry() -> io::Result<()> {
+    Ok(())
+}
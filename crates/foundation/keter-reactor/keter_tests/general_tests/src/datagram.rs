@@ -0,0 +1,73 @@
+// MIT/Apache2 License
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use keter_reactor::platform::poll_io::Async;
+
+use std::io;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use std::net::UdpSocket;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use std::os::unix::net::UnixDatagram;
+
+pub(crate) async fn test() {
+    entry().await.unwrap();
+}
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+async fn entry() -> io::Result<()> {
+    // Send datagrams over UDP.
+    let socket1 = Async::<UdpSocket>::bind(([127, 0, 0, 1], 0))?;
+    let socket2 = Async::<UdpSocket>::bind(([127, 0, 0, 1], 0))?;
+    let addr1 = socket1.get_ref().local_addr()?;
+    let addr2 = socket2.get_ref().local_addr()?;
+
+    socket1.send_to(b"datagram", addr2).await?;
+    let mut buf = [0u8; 16];
+    let (len, from) = socket2.peek_from(&mut buf).await?;
+    assert_eq!((&buf[..len], from), (&b"datagram"[..], addr1));
+    let (len, from) = socket2.recv_from(&mut buf).await?;
+    assert_eq!((&buf[..len], from), (&b"datagram"[..], addr1));
+
+    // Connected UDP sockets.
+    socket1.connect(addr2)?;
+    socket2.connect(addr1)?;
+    socket2.send(b"reply").await?;
+    let len = socket1.peek(&mut buf).await?;
+    assert_eq!(&buf[..len], b"reply");
+    let len = socket1.recv(&mut buf).await?;
+    assert_eq!(&buf[..len], b"reply");
+
+    // Send datagrams over a Unix socket pair.
+    let (left, right) = Async::<UnixDatagram>::pair()?;
+    left.send(b"paired").await?;
+    let len = right.recv(&mut buf).await?;
+    assert_eq!(&buf[..len], b"paired");
+
+    // Send datagrams to a bound Unix socket from an unbound one.
+    let dir = std::env::temp_dir().join(format!("keter-datagram-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("socket");
+    let _ = std::fs::remove_file(&path);
+
+    let bound = Async::<UnixDatagram>::bind(&path)?;
+    let unbound = Async::<UnixDatagram>::unbound()?;
+    unbound.send_to(b"unbound", &path).await?;
+    let (len, from) = bound.recv_from(&mut buf).await?;
+    assert_eq!(&buf[..len], b"unbound");
+    assert!(from.is_unnamed());
+
+    unbound.connect(&path)?;
+    unbound.send(b"connected").await?;
+    let len = bound.recv(&mut buf).await?;
+    assert_eq!(&buf[..len], b"connected");
+
+    drop(bound);
+    std::fs::remove_dir_all(&dir)?;
+
+    Ok(())
+}
+
+#[cfg(not(all(unix, not(target_vendor = "apple"), not(target_os = "android"))))]
+async fn entry() -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,60 @@
+// MIT/Apache2 License
+
+use futures_lite::future;
+use keter_reactor::{spawn, spawn_local};
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+pub(crate) async fn test() {
+    // Local tasks can hold non-`Send` data and run alongside the main future.
+    let counter = Rc::new(Cell::new(0));
+    let handles = (0..10)
+        .map(|i| {
+            let counter = counter.clone();
+            spawn_local(async move {
+                future::yield_now().await;
+                counter.set(counter.get() + 1);
+                i * 2
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut total = 0;
+    for handle in handles {
+        total += handle.await;
+    }
+    assert_eq!(total, 90);
+    assert_eq!(counter.get(), 10);
+
+    // Detached tasks keep running.
+    let (send, recv) = std::sync::mpsc::channel();
+    spawn_local(async move {
+        future::yield_now().await;
+        send.send(()).unwrap();
+    })
+    .detach();
+    while recv.try_recv().is_err() {
+        future::yield_now().await;
+    }
+
+    // Cancelled tasks stop running.
+    let ran = Rc::new(Cell::new(false));
+    let handle = spawn_local({
+        let ran = ran.clone();
+        async move {
+            future::pending::<()>().await;
+            ran.set(true);
+        }
+    });
+    future::yield_now().await;
+    assert!(!handle.is_finished());
+    assert_eq!(handle.cancel().await, None);
+    assert!(!ran.get());
+
+    // Send tasks can be spawned from other threads.
+    let handle = std::thread::spawn(|| spawn(async { 1 + 2 }))
+        .join()
+        .unwrap();
+    assert_eq!(handle.await, 3);
+}
@@ -0,0 +1,64 @@
+// MIT/Apache2 License
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use futures_lite::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use keter_reactor::platform::poll_io::Async;
+
+use std::io;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use std::os::unix::io::AsFd;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use std::os::unix::net::UnixStream;
+
+pub(crate) async fn test() {
+    entry().await.unwrap();
+}
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+async fn entry() -> io::Result<()> {
+    let (left, right) = Async::<UnixStream>::pair()?;
+
+    // Send one end of another socket pair across the first pair.
+    let (mut inner_left, inner_right) = Async::<UnixStream>::pair()?;
+    let sent = left
+        .send_with_fds(b"fds", &[inner_right.get_ref().as_fd()])
+        .await?;
+    assert_eq!(sent, 3);
+    drop(inner_right);
+
+    let mut buf = [0u8; 8];
+    let mut fds = Vec::new();
+    let len = right.recv_with_fds(&mut buf, &mut fds).await?;
+    assert_eq!(&buf[..len], b"fds");
+    assert_eq!(fds.len(), 1);
+
+    // The received file descriptor is connected to the original.
+    let mut received = Async::new(UnixStream::from(fds.pop().unwrap()))?;
+    inner_left.write_all(b"through").await?;
+    let mut buf = [0u8; 7];
+    received.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"through");
+
+    // Sending without file descriptors works as well.
+    left.send_with_fds(b"plain", &[]).await?;
+    let len = right.recv_with_fds(&mut buf, &mut fds).await?;
+    assert_eq!(&buf[..len], b"plain");
+    assert!(fds.is_empty());
+
+    // Our peer is ourselves.
+    #[cfg(target_os = "linux")]
+    {
+        let cred = left.peer_cred()?;
+        assert_eq!(cred.pid, std::process::id());
+        assert_eq!(cred.uid, rustix::process::getuid().as_raw());
+        assert_eq!(cred.gid, rustix::process::getgid().as_raw());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(all(unix, not(target_vendor = "apple"), not(target_os = "android"))))]
+async fn entry() -> io::Result<()> {
+    Ok(())
+}
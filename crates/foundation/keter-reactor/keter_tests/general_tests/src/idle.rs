@@ -0,0 +1,104 @@
+// MIT/Apache2 License
+
+use futures_lite::future;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use futures_lite::{AsyncReadExt, AsyncWriteExt};
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use keter_reactor::platform::poll_io::Async;
+use keter_reactor::{idle, on_before_sleep, request_idle_callback, spawn_local, Timer};
+use web_time::{Duration, Instant};
+
+use std::cell::{Cell, RefCell};
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+
+pub(crate) async fn test() {
+    // The reactor only goes idle once other work is done.
+    let steps = Rc::new(Cell::new(0));
+    let task = spawn_local({
+        let steps = steps.clone();
+        async move {
+            for _ in 0..10 {
+                steps.set(steps.get() + 1);
+                future::yield_now().await;
+            }
+        }
+    });
+    idle().await;
+    assert_eq!(steps.get(), 10);
+    task.await;
+
+    // This holds for tasks that run for longer than one tick of the executor.
+    let steps = Rc::new(Cell::new(0));
+    let task = spawn_local({
+        let steps = steps.clone();
+        async move {
+            for _ in 0..1000 {
+                steps.set(steps.get() + 1);
+                future::yield_now().await;
+            }
+        }
+    });
+    idle().await;
+    assert_eq!(steps.get(), 1000);
+    task.await;
+
+    // Tasks waiting on I/O do not keep the reactor awake.
+    waiting_on_io().await;
+
+    // Hooks run before the reactor sleeps, until they are removed.
+    let sleeps = Rc::new(Cell::new(0));
+    let hook = on_before_sleep({
+        let sleeps = sleeps.clone();
+        move || sleeps.set(sleeps.get() + 1)
+    });
+    Timer::after(Duration::from_millis(10)).await;
+    let count = sleeps.get();
+    assert!(count > 0);
+    drop(hook);
+    Timer::after(Duration::from_millis(10)).await;
+    assert_eq!(sleeps.get(), count);
+
+    // Idle callbacks run in order with a time budget.
+    let order = Rc::new(RefCell::new(Vec::new()));
+    for i in 0..3 {
+        let order = order.clone();
+        request_idle_callback(None, move |deadline| {
+            assert!(!deadline.did_timeout());
+            order.borrow_mut().push(i);
+        });
+    }
+    Timer::after(Duration::from_millis(10)).await;
+    assert_eq!(*order.borrow(), [0, 1, 2]);
+
+    // Callbacks run once their deadline passes, even if the reactor never goes idle.
+    let timed_out = Rc::new(Cell::new(None));
+    let start = Instant::now();
+    request_idle_callback(Some(start + Duration::from_millis(20)), {
+        let timed_out = timed_out.clone();
+        move |deadline| timed_out.set(Some(deadline.did_timeout()))
+    });
+    while timed_out.get().is_none() {
+        future::yield_now().await;
+    }
+    assert_eq!(timed_out.get(), Some(true));
+    assert!(start.elapsed() >= Duration::from_millis(20));
+}
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+async fn waiting_on_io() {
+    let (mut left, mut right) = Async::<UnixStream>::pair().unwrap();
+    let reader = spawn_local(async move {
+        let mut buf = [0];
+        left.read_exact(&mut buf).await.unwrap();
+        buf[0]
+    });
+    idle().await;
+    assert!(!reader.is_finished());
+    right.write_all(&[7]).await.unwrap();
+    assert_eq!(reader.await, 7);
+}
+
+#[cfg(not(all(unix, not(target_vendor = "apple"), not(target_os = "android"))))]
+async fn waiting_on_io() {}
@@ -1,15 +1,28 @@
 // MIT/Apache2 License
 
 mod async_io;
+mod connect;
+mod datagram;
+mod executor;
+mod fd_passing;
+mod idle;
+mod process;
+mod proxy;
+mod signal;
 mod timer;
 
 use futures_lite::future;
-use keter_reactor::{exit, main, Main, Reactor};
-use macro_rules_attribute::apply;
+use keter_reactor::{exit, on_exit, Main, Reactor};
 
-#[apply(main!)]
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+#[keter_reactor::attr::main]
 fn main(reactor: Reactor) -> Main {
     keter_test::run_tests(|harness| {
+        let (proxy, messages) = reactor.proxy();
+        let cleaned_up = Rc::new(Cell::new(false));
         let result = reactor.block_on(async {
             // Successful launch.
             harness.test("starts_up", async {}).await;
@@ -19,7 +32,7 @@ fn main(reactor: Reactor) -> Main {
 
             // Group of tests.
             harness
-                .group("functionality", 2, async {
+                .group("functionality", 10, async {
                     // Handle async I/O.
                     harness
                         .test("async_io", async {
@@ -27,6 +40,62 @@ fn main(reactor: Reactor) -> Main {
                         })
                         .await;
 
+                    // Connect to hosts by name.
+                    harness
+                        .test("connect", async {
+                            connect::test().await;
+                        })
+                        .await;
+
+                    // Handle datagram sockets.
+                    harness
+                        .test("datagram", async {
+                            datagram::test().await;
+                        })
+                        .await;
+
+                    // Spawn tasks.
+                    harness
+                        .test("executor", async {
+                            executor::test().await;
+                        })
+                        .await;
+
+                    // Pass file descriptors over Unix sockets.
+                    harness
+                        .test("fd_passing", async {
+                            fd_passing::test().await;
+                        })
+                        .await;
+
+                    // Run work when the reactor is idle.
+                    harness
+                        .test("idle", async {
+                            idle::test().await;
+                        })
+                        .await;
+
+                    // Spawn child processes.
+                    harness
+                        .test("process", async {
+                            process::test().await;
+                        })
+                        .await;
+
+                    // Send work from other threads.
+                    harness
+                        .test("proxy", async {
+                            proxy::test(proxy, messages).await;
+                        })
+                        .await;
+
+                    // Handle Unix signals.
+                    harness
+                        .test("signal", async {
+                            signal::test().await;
+                        })
+                        .await;
+
                     // Handle timers.
                     harness
                         .test("timer", async {
@@ -36,11 +105,19 @@ fn main(reactor: Reactor) -> Main {
                 })
                 .await;
 
+            // Clean up once the reactor exits.
+            let cleaned_up = cleaned_up.clone();
+            on_exit(async move {
+                keter_reactor::Timer::after(Duration::from_millis(1)).await;
+                cleaned_up.set(true);
+            });
+
             exit().await
         });
 
         futures_lite::future::block_on(harness.test("finishes_properly", async {
-            assert!(result.is_ok());
+            assert!(result.as_ref().unwrap().is_success());
+            assert!(cleaned_up.get());
         }));
 
         result
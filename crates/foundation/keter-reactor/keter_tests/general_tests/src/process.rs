@@ -0,0 +1,55 @@
+// MIT/Apache2 License
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use futures_lite::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use keter_reactor::platform::process::{Command, Stdio};
+
+use std::io;
+
+pub(crate) async fn test() {
+    entry().await.unwrap();
+}
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+async fn entry() -> io::Result<()> {
+    // Exit codes are reported.
+    let status = Command::new("sh").args(["-c", "exit 3"]).status().await?;
+    assert_eq!(status.code(), Some(3));
+
+    // Output is collected.
+    let output = Command::new("sh")
+        .args(["-c", "echo out; echo err >&2"])
+        .output()
+        .await?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"out\n");
+    assert_eq!(output.stderr, b"err\n");
+
+    // Piped I/O works through the reactor.
+    let mut child = Command::new("cat")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    stdin.write_all(b"echoed").await?;
+    drop(stdin);
+    let mut buf = Vec::new();
+    stdout.read_to_end(&mut buf).await?;
+    assert_eq!(buf, b"echoed");
+    assert!(child.status().await?.success());
+
+    // Killed children are reported as such.
+    let mut child = Command::new("sleep").arg("60").spawn()?;
+    assert!(child.try_status()?.is_none());
+    child.kill()?;
+    assert!(!child.status().await?.success());
+
+    Ok(())
+}
+
+#[cfg(not(all(unix, not(target_vendor = "apple"), not(target_os = "android"))))]
+async fn entry() -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,46 @@
+// MIT/Apache2 License
+
+use futures_lite::prelude::*;
+use keter_reactor::{Messages, Proxy};
+
+use std::thread;
+
+pub(crate) async fn test(proxy: Proxy<u32>, mut messages: Messages<u32>) {
+    let reactor_thread = thread::current().id();
+
+    // Send messages and closures from a background thread.
+    let (posted_send, posted_recv) = std::sync::mpsc::channel();
+    let worker = thread::spawn({
+        let proxy = proxy.clone();
+        move || {
+            for i in 0..5 {
+                proxy.send(i).unwrap();
+            }
+
+            proxy
+                .post(move || {
+                    posted_send.send(thread::current().id()).unwrap();
+                })
+                .unwrap();
+            proxy.wake().unwrap();
+        }
+    });
+
+    // Messages arrive in order on the reactor thread.
+    let received = (&mut messages).take(5).collect::<Vec<_>>().await;
+    assert_eq!(received, [0, 1, 2, 3, 4]);
+    worker.join().unwrap();
+
+    // The posted closure runs on the reactor thread.
+    let ran_on = loop {
+        match posted_recv.try_recv() {
+            Ok(id) => break id,
+            Err(_) => futures_lite::future::yield_now().await,
+        }
+    };
+    assert_eq!(ran_on, reactor_thread);
+
+    // Once the stream is gone, sending fails.
+    drop(messages);
+    assert_eq!(proxy.send(5).unwrap_err().0, 5);
+}
@@ -0,0 +1,29 @@
+// MIT/Apache2 License
+
+pub(crate) async fn test() {
+    #[cfg(target_os = "linux")]
+    {
+        use futures_lite::prelude::*;
+        use keter_reactor::platform::signal::{Signal, Signals};
+        use rustix::process::{getpid, kill_process, Signal as RawSignal};
+
+        let mut signals = Signals::new([Signal::Usr1, Signal::Usr2]).unwrap();
+
+        // Send ourselves a signal and wait for it to arrive.
+        kill_process(getpid(), RawSignal::USR1).unwrap();
+        assert_eq!(signals.next().await.unwrap().unwrap(), Signal::Usr1);
+
+        // Removed signals are no longer received.
+        signals.remove_signals([Signal::Usr1]).unwrap();
+        signals.add_signals([Signal::Hup]).unwrap();
+        kill_process(getpid(), RawSignal::HUP).unwrap();
+        kill_process(getpid(), RawSignal::USR2).unwrap();
+
+        let mut received = vec![
+            signals.next().await.unwrap().unwrap(),
+            signals.next().await.unwrap().unwrap(),
+        ];
+        received.sort();
+        assert_eq!(received, [Signal::Hup, Signal::Usr2]);
+    }
+}
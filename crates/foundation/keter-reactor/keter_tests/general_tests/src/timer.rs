@@ -1,7 +1,15 @@
 // MIT/Apache2 License
 
 use futures_lite::prelude::*;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use keter_reactor::platform::any_thread::ReactorBuilderExt as _;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use keter_reactor::platform::instantiation::ReactorBuilderExt as _;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use keter_reactor::time::{self, VirtualClock};
 use keter_reactor::Timer;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+use keter_reactor::{exit, ReactorBuilder};
 use web_time::{Duration, Instant};
 
 pub(crate) async fn test() {
@@ -21,4 +29,49 @@ pub(crate) async fn test() {
     timer.next().await;
     let elapsed = start.elapsed();
     assert!(elapsed >= period * 2 && elapsed - period * 2 < jitter);
+
+    // The same timers fire exactly on time against a virtual clock.
+    virtual_clock().await;
+}
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+async fn virtual_clock() {
+    // Run the timers on another reactor, so that they fire without waiting.
+    let clock = VirtualClock::new().with_auto_advance(true);
+    let real_start = Instant::now();
+    let reactor = std::thread::spawn({
+        let clock = clock.clone();
+        move || {
+            let reactor = ReactorBuilder::new()
+                .any_thread(true)
+                .virtual_clock(clock)
+                .build();
+
+            reactor.block_on(async {
+                let start = time::now();
+                Timer::after(Duration::from_secs(2)).await;
+                assert_eq!(time::now() - start, Duration::from_secs(2));
+
+                let period = Duration::from_secs(1);
+                let start = time::now();
+                let mut timer = Timer::interval(period);
+                timer.next().await;
+                assert_eq!(time::now() - start, period);
+                timer.next().await;
+                assert_eq!(time::now() - start, period * 2);
+
+                // Stop the interval, so the clock stays where it is while exiting.
+                drop(timer);
+                exit().await
+            })
+        }
+    });
+
+    let finished = reactor.join().unwrap().unwrap();
+    assert!(finished.is_success());
+    assert_eq!(clock.elapsed(), Duration::from_secs(4));
+    assert!(real_start.elapsed() < Duration::from_secs(2));
 }
+
+#[cfg(not(all(unix, not(target_vendor = "apple"), not(target_os = "android"))))]
+async fn virtual_clock() {}
@@ -0,0 +1,46 @@
+// MIT/Apache2 License
+
+mod window;
+mod xvfb;
+
+use keter_reactor::{exit, Main, Reactor};
+
+#[keter_reactor::attr::main]
+fn main(reactor: Reactor) -> Main {
+    keter_test::run_tests(|harness| {
+        reactor.block_on(async {
+            // Start a virtual X server, unless one is already running.
+            let server = xvfb::Server::start().await;
+            harness
+                .test("xvfb", async {
+                    if let Err(err) = &server {
+                        panic!("failed to start Xvfb: {err}");
+                    }
+                })
+                .await;
+
+            if let Ok(server) = &server {
+                harness
+                    .group("x11", 2, async {
+                        // Map a window and wait for it to be exposed.
+                        harness
+                            .test("map_window", async {
+                                window::map(server.display()).await;
+                            })
+                            .await;
+
+                        // Destroy a window and wait for it to be gone.
+                        harness
+                            .test("destroy_window", async {
+                                window::destroy(server.display()).await;
+                            })
+                            .await;
+                    })
+                    .await;
+            }
+
+            drop(server);
+            exit().await
+        })
+    })
+}
@@ -0,0 +1,119 @@
+// MIT/Apache2 License
+
+use keter_reactor::platform::x11::x11rb::connection::Connection as _;
+use keter_reactor::platform::x11::x11rb::protocol::xproto::{
+    ConnectionExt as _, CreateWindowAux, EventMask, WindowClass,
+};
+use keter_reactor::platform::x11::x11rb::protocol::Event;
+use keter_reactor::platform::x11::x11rb::COPY_DEPTH_FROM_PARENT;
+use keter_reactor::platform::x11::Connection;
+use keter_reactor::time::timeout;
+
+use std::io;
+use web_time::Duration;
+
+/// How long to wait for the server to send an event.
+const EVENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub(crate) async fn map(display: &str) {
+    map_entry(display).await.unwrap();
+}
+
+pub(crate) async fn destroy(display: &str) {
+    destroy_entry(display).await.unwrap();
+}
+
+async fn map_entry(display: &str) -> io::Result<()> {
+    let connection = Connection::connect(Some(display))?;
+    let window = create_window(&connection)?;
+
+    // The reactor flushes the request before it goes to sleep.
+    connection
+        .get_ref()
+        .map_window(window)
+        .map_err(io::Error::other)?;
+
+    // The window is mapped before it is exposed.
+    let mut mapped = false;
+    loop {
+        match next_event(&connection).await? {
+            Event::MapNotify(event) => {
+                assert_eq!(event.window, window);
+                mapped = true;
+            }
+            Event::Expose(event) => {
+                assert_eq!(event.window, window);
+                assert!(mapped, "window was exposed before it was mapped");
+                break;
+            }
+            event => check_event(event),
+        }
+    }
+
+    Ok(())
+}
+
+async fn destroy_entry(display: &str) -> io::Result<()> {
+    let connection = Connection::connect(Some(display))?;
+    let window = create_window(&connection)?;
+    let conn = connection.get_ref();
+    conn.map_window(window).map_err(io::Error::other)?;
+    conn.destroy_window(window).map_err(io::Error::other)?;
+
+    // Waiting on a reply reads the events into memory, so the reactor has to deliver them
+    // without the socket becoming readable.
+    conn.get_input_focus()
+        .map_err(io::Error::other)?
+        .reply()
+        .map_err(io::Error::other)?;
+
+    loop {
+        match next_event(&connection).await? {
+            Event::DestroyNotify(event) => {
+                assert_eq!(event.window, window);
+                break;
+            }
+            event => check_event(event),
+        }
+    }
+
+    Ok(())
+}
+
+/// Create a window that reports exposure and structure events.
+fn create_window(connection: &Connection) -> io::Result<u32> {
+    let conn = connection.get_ref();
+    let screen = &conn.setup().roots[connection.screen_num()];
+    let window = conn.generate_id().map_err(io::Error::other)?;
+
+    conn.create_window(
+        COPY_DEPTH_FROM_PARENT,
+        window,
+        screen.root,
+        0,
+        0,
+        100,
+        100,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        0,
+        &CreateWindowAux::new()
+            .background_pixel(screen.white_pixel)
+            .event_mask(EventMask::EXPOSURE | EventMask::STRUCTURE_NOTIFY),
+    )
+    .map_err(io::Error::other)?;
+
+    Ok(window)
+}
+
+/// Wait for the next event, failing if the server takes too long.
+async fn next_event(connection: &Connection) -> io::Result<Event> {
+    timeout(EVENT_TIMEOUT, connection.event()).await?
+}
+
+/// Fail on errors sent by the server.
+fn check_event(event: Event) {
+    if let Event::Error(error) = event {
+        panic!("X11 error: {error:?}");
+    }
+}
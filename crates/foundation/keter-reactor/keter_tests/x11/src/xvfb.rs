@@ -0,0 +1,77 @@
+// MIT/Apache2 License
+
+use keter_reactor::platform::process::{Child, Command, Stdio};
+use keter_reactor::Timer;
+
+use std::env;
+use std::io;
+use std::path::Path;
+use web_time::Duration;
+
+/// An X server for the tests to connect to.
+pub(crate) struct Server {
+    /// The name of the display.
+    display: String,
+
+    /// The Xvfb process, if we started one.
+    child: Option<Child>,
+}
+
+impl Server {
+    /// Use the X server in `DISPLAY`, or start Xvfb if there isn't one.
+    pub(crate) async fn start() -> io::Result<Self> {
+        if let Some(display) = env::var("DISPLAY").ok().filter(|d| !d.is_empty()) {
+            return Ok(Self {
+                display,
+                child: None,
+            });
+        }
+
+        // Find a display number that is not taken.
+        let number = (99..)
+            .find(|n| !Path::new(&format!("/tmp/.X11-unix/X{n}")).exists())
+            .unwrap();
+        let display = format!(":{number}");
+        let mut child = Command::new("Xvfb")
+            .arg(&display)
+            .args(["-screen", "0", "640x480x24", "-nolisten", "tcp"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        // Wait for the server to start listening.
+        let socket = format!("/tmp/.X11-unix/X{number}");
+        for _ in 0..100 {
+            if Path::new(&socket).exists() {
+                return Ok(Self {
+                    display,
+                    child: Some(child),
+                });
+            }
+            if let Some(status) = child.try_status()? {
+                return Err(io::Error::other(format!("Xvfb exited early: {status}")));
+            }
+
+            Timer::after(Duration::from_millis(100)).await;
+        }
+
+        child.kill().ok();
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "Xvfb did not start listening",
+        ))
+    }
+
+    /// Get the name of the display.
+    pub(crate) fn display(&self) -> &str {
+        &self.display
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            child.kill().ok();
+        }
+    }
+}
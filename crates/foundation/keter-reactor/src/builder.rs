@@ -0,0 +1,258 @@
+// MIT/Apache2 License
+
+//! Configuration for creating a [`Reactor`].
+//!
+//! [`Reactor`]: crate::Reactor
+
+use crate::sim::Simulation;
+use crate::sys;
+use crate::time::VirtualClock;
+
+use std::fmt;
+use std::sync::Arc;
+
+use web_time::Duration;
+
+/// A builder for configuring a [`Reactor`].
+///
+/// Options that only apply to some platforms are set through the extension traits in
+/// [`platform`]. On platforms where the [`Reactor`] can be created freely, it is built
+/// using [`platform::instantiation::ReactorBuilderExt::build`].
+///
+/// [`Reactor`]: crate::Reactor
+/// [`platform`]: crate::platform
+/// [`platform::instantiation::ReactorBuilderExt::build`]: crate::platform::instantiation::ReactorBuilderExt::build
+pub struct ReactorBuilder {
+    /// Platform-independent configuration.
+    pub(crate) config: Config,
+
+    /// Platform-specific configuration.
+    pub(crate) settings: sys::Options,
+}
+
+impl fmt::Debug for ReactorBuilder {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReactorBuilder")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ReactorBuilder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReactorBuilder {
+    /// Create a new builder with the default configuration.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            config: Config::default(),
+            settings: sys::Options::default(),
+        }
+    }
+
+    /// Set the name of the reactor.
+    ///
+    /// The name is passed to [`Instrument`] hooks to tell reactors apart.
+    #[inline]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.config.name = Some(name.into().into());
+        self
+    }
+
+    /// Allow timers to fire up to `slack` late so that nearby timers fire together.
+    ///
+    /// This applies to timers created or reset while the reactor is running. Deadlines
+    /// are rounded up to a multiple of `slack`, so every timer due within the same window
+    /// is fired in a single wakeup. Larger values mean fewer wakeups at the cost of
+    /// precision. The default is no slack.
+    #[inline]
+    pub fn timer_slack(mut self, slack: Duration) -> Self {
+        self.config.timer_slack = slack;
+        self
+    }
+
+    /// Set how long the reactor must expect to sleep for before it is considered idle.
+    ///
+    /// If the next timer is due sooner than this, the reactor is still considered busy.
+    /// The default is zero, meaning the reactor is idle whenever it is about to sleep.
+    #[inline]
+    pub fn idle_threshold(mut self, threshold: Duration) -> Self {
+        self.config.idle_threshold = threshold;
+        self
+    }
+
+    /// Set how long the hooks registered with [`on_exit`] are given to run.
+    ///
+    /// Hooks that are still running once the deadline passes are dropped. The default is
+    /// five seconds.
+    ///
+    /// [`on_exit`]: crate::on_exit
+    #[inline]
+    pub fn exit_deadline(mut self, deadline: Duration) -> Self {
+        self.config.exit_deadline = deadline;
+        self
+    }
+
+    /// Run the reactor's timers against a [`VirtualClock`] instead of the real time.
+    ///
+    /// This is meant for tests that need to check timer behavior without waiting.
+    ///
+    /// [`VirtualClock`]: crate::time::VirtualClock
+    #[inline]
+    pub fn virtual_clock(mut self, clock: VirtualClock) -> Self {
+        self.config.clock = Some(clock);
+        self
+    }
+
+    /// Run the reactor inside of a deterministic [`Simulation`].
+    ///
+    /// This also runs the reactor's timers against the simulation's clock.
+    ///
+    /// [`Simulation`]: crate::sim::Simulation
+    #[inline]
+    pub fn simulation(mut self, simulation: Simulation) -> Self {
+        self.config.clock = Some(simulation.clock().clone());
+        self.config.simulation = Some(simulation);
+        self
+    }
+
+    /// Add hooks for observing the reactor.
+    ///
+    /// This can be called several times to add several sets of hooks.
+    #[inline]
+    pub fn instrument(mut self, instrument: impl Instrument) -> Self {
+        self.config.instruments.push(Arc::new(instrument));
+        self
+    }
+}
+
+/// Hooks for observing what a [`Reactor`] is doing.
+///
+/// Each hook is passed the name of the reactor set through [`ReactorBuilder::name`].
+/// All hooks do nothing by default.
+///
+/// [`Reactor`]: crate::Reactor
+pub trait Instrument: Send + Sync + 'static {
+    /// Called when the reactor starts running.
+    #[inline]
+    fn on_start(&self, name: Option<&str>) {
+        let _ = name;
+    }
+
+    /// Called after the reactor polls its futures, with the time spent polling.
+    #[inline]
+    fn on_poll(&self, name: Option<&str>, busy: Duration) {
+        let _ = (name, busy);
+    }
+
+    /// Called when the reactor stops running.
+    ///
+    /// `exit_code` is the exit code if the reactor was told to exit.
+    #[inline]
+    fn on_stop(&self, name: Option<&str>, exit_code: Option<i32>) {
+        let _ = (name, exit_code);
+    }
+}
+
+/// Platform-independent configuration for the reactor.
+pub(crate) struct Config {
+    /// The name of the reactor.
+    pub(crate) name: Option<Arc<str>>,
+
+    /// How late timers are allowed to fire.
+    pub(crate) timer_slack: Duration,
+
+    /// How long the reactor must expect to sleep for to be idle.
+    pub(crate) idle_threshold: Duration,
+
+    /// How long the exit hooks are given to run.
+    pub(crate) exit_deadline: Duration,
+
+    /// The virtual clock to run timers against.
+    pub(crate) clock: Option<VirtualClock>,
+
+    /// The simulation to run the reactor in.
+    pub(crate) simulation: Option<Simulation>,
+
+    /// Hooks for observing the reactor.
+    pub(crate) instruments: Vec<Arc<dyn Instrument>>,
+}
+
+impl fmt::Debug for Config {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("name", &self.name)
+            .field("timer_slack", &self.timer_slack)
+            .field("idle_threshold", &self.idle_threshold)
+            .field("exit_deadline", &self.exit_deadline)
+            .field("clock", &self.clock)
+            .field("simulation", &self.simulation)
+            .field("instruments", &self.instruments.len())
+            .finish()
+    }
+}
+
+impl Default for Config {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            name: None,
+            timer_slack: Duration::ZERO,
+            idle_threshold: Duration::ZERO,
+            exit_deadline: crate::shutdown::DEFAULT_DEADLINE,
+            clock: None,
+            simulation: None,
+            instruments: Vec::new(),
+        }
+    }
+}
+
+#[cfg(all(test, unix, not(target_vendor = "apple"), not(target_os = "android")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder() {
+        use crate::{exit_with, test_reactor};
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<String>>);
+
+        impl Instrument for Arc<Recorder> {
+            fn on_start(&self, name: Option<&str>) {
+                self.0.lock().unwrap().push(format!("start {name:?}"));
+            }
+
+            fn on_poll(&self, _name: Option<&str>, _busy: Duration) {
+                self.0.lock().unwrap().push("poll".into());
+            }
+
+            fn on_stop(&self, name: Option<&str>, exit_code: Option<i32>) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("stop {name:?} {exit_code:?}"));
+            }
+        }
+
+        let recorder = Arc::new(Recorder::default());
+        let reactor = test_reactor(|builder| builder.name("builder").instrument(recorder.clone()));
+        assert_eq!(reactor.name(), Some("builder"));
+
+        let finished = reactor.block_on(async { exit_with(8).await }).unwrap();
+        assert_eq!(finished.exit_code(), 8);
+
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(events.first().unwrap(), "start Some(\"builder\")");
+        assert!(events.iter().any(|event| event == "poll"));
+        assert_eq!(events.last().unwrap(), "stop Some(\"builder\") Some(8)");
+    }
+}
@@ -0,0 +1,38 @@
+// MIT/Apache2 License
+
+//! The task executor driven alongside the reactor.
+
+use async_executor::{Executor, LocalExecutor};
+
+use std::future::Future;
+use std::rc::Rc;
+
+pub(crate) use async_task::Task;
+
+/// Executor for tasks that can be sent between threads.
+static GLOBAL: Executor<'static> = Executor::new();
+
+thread_local! {
+    /// Executor for tasks that are pinned to this thread.
+    static LOCAL: Rc<LocalExecutor<'static>> = Rc::new(LocalExecutor::new());
+}
+
+/// Spawn a task onto the current thread's executor.
+#[inline]
+pub(crate) fn spawn_local<T: 'static>(future: impl Future<Output = T> + 'static) -> Task<T> {
+    LOCAL.with(|local| local.spawn(future))
+}
+
+/// Spawn a task onto the global executor.
+#[inline]
+pub(crate) fn spawn<T: Send + 'static>(
+    future: impl Future<Output = T> + Send + 'static,
+) -> Task<T> {
+    GLOBAL.spawn(future)
+}
+
+/// Run spawned tasks while waiting for the given future to complete.
+pub(crate) async fn run<T>(future: impl Future<Output = T>) -> T {
+    let local = LOCAL.with(Rc::clone);
+    local.run(GLOBAL.run(future)).await
+}
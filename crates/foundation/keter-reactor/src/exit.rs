@@ -0,0 +1,180 @@
+// MIT/Apache2 License
+
+//! Exit signals for each reactor, and tracking of which reactor is running.
+
+use event_listener::{Event, EventListener};
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+thread_local! {
+    /// The exit signals of the reactors running on this thread, innermost last.
+    static CURRENT: RefCell<Vec<Arc<Signal>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Get the exit signal of the innermost reactor running on this thread.
+#[inline]
+pub(crate) fn current() -> Option<Arc<Signal>> {
+    CURRENT.with(|current| current.borrow().last().cloned())
+}
+
+/// Set in [`Signal::stop`] once the reactor has been told to stop.
+const STOPPED: u64 = 1 << 32;
+
+/// A signal to indicate that a reactor is stopping.
+pub(crate) struct Signal {
+    /// Whether the reactor is currently running.
+    running: AtomicBool,
+
+    /// Whether to stop running, and the exit code requested by the first stop.
+    ///
+    /// The exit code is in the low 32 bits, and [`STOPPED`] is set once stopped. Keeping
+    /// both in one atomic means that only the first stop can set the exit code.
+    stop: AtomicU64,
+
+    /// The signal.
+    stop_ops: Event,
+}
+
+impl Signal {
+    /// Create a new signal for a reactor.
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self {
+            running: AtomicBool::new(false),
+            stop: AtomicU64::new(0),
+            stop_ops: Event::new(),
+        }
+    }
+
+    /// Mark the reactor as running until the guard is dropped.
+    ///
+    /// Any stop requested before this point is discarded. Fails if the reactor is already
+    /// running.
+    pub(crate) fn start(self: &Arc<Self>) -> io::Result<RunGuard> {
+        if self.running.swap(true, Ordering::Acquire) {
+            return Err(io::Error::other("the reactor is already running"));
+        }
+
+        self.stop.store(0, Ordering::Release);
+        Ok(RunGuard(self.clone()))
+    }
+
+    /// Wait for the signal to be sent, returning the requested exit code.
+    ///
+    /// The signal stays set until the reactor is run again.
+    pub(crate) async fn wait(&self) -> i32 {
+        let listener = EventListener::new();
+        futures_lite::pin!(listener);
+
+        loop {
+            // Do we need to stop running?
+            if let Some(exit_code) = self.exit_code(Ordering::Acquire) {
+                return exit_code;
+            }
+
+            // Establish the listener.
+            listener.as_mut().listen(&self.stop_ops);
+
+            // Check again.
+            if let Some(exit_code) = self.exit_code(Ordering::SeqCst) {
+                return exit_code;
+            }
+
+            // Wait on the listener.
+            listener.as_mut().await;
+        }
+    }
+
+    /// Send the signal with the exit code to use.
+    #[cold]
+    pub(crate) fn stop(&self, exit_code: i32) {
+        // Only the first stop of a run sets the exit code.
+        let stopped = STOPPED | u64::from(exit_code as u32);
+        if self
+            .stop
+            .compare_exchange(0, stopped, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            self.stop_ops.notify_additional(usize::MAX);
+        }
+    }
+
+    /// Get the exit code if the signal has been sent.
+    #[inline]
+    fn exit_code(&self, ordering: Ordering) -> Option<i32> {
+        let stop = self.stop.load(ordering);
+        (stop & STOPPED != 0).then_some(stop as u32 as i32)
+    }
+}
+
+/// Marks a reactor as running until dropped.
+pub(crate) struct RunGuard(Arc<Signal>);
+
+impl RunGuard {
+    /// Make this the innermost reactor on this thread until the scope is dropped.
+    #[inline]
+    pub(crate) fn enter(&self) -> CurrentScope {
+        CURRENT.with(|current| current.borrow_mut().push(self.0.clone()));
+        CurrentScope(self.0.clone())
+    }
+}
+
+impl Drop for RunGuard {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.running.store(false, Ordering::Release);
+    }
+}
+
+/// Keeps a reactor as the innermost one on this thread until dropped.
+pub(crate) struct CurrentScope(Arc<Signal>);
+
+impl Drop for CurrentScope {
+    #[inline]
+    fn drop(&mut self) {
+        CURRENT.with(|current| {
+            let popped = current.borrow_mut().pop();
+            debug_assert!(popped.is_some_and(|signal| Arc::ptr_eq(&signal, &self.0)));
+        });
+    }
+}
+
+/// A handle for stopping a specific [`Reactor`] from any thread.
+///
+/// [`Reactor`]: crate::Reactor
+#[derive(Clone)]
+pub struct ExitHandle(pub(crate) Arc<Signal>);
+
+impl fmt::Debug for ExitHandle {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExitHandle")
+            .field("running", &self.0.running.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExitHandle {
+    /// Tell the reactor to exit as soon as possible.
+    ///
+    /// This is equivalent to `exit_with(0)`.
+    #[inline]
+    pub fn exit(&self) {
+        self.exit_with(0);
+    }
+
+    /// Tell the reactor to exit as soon as possible with an exit code.
+    ///
+    /// This has no effect if the reactor is not running, or if it has already been told
+    /// to exit during this run.
+    #[inline]
+    pub fn exit_with(&self, code: i32) {
+        if self.0.running.load(Ordering::Acquire) {
+            self.0.stop(code);
+        }
+    }
+}
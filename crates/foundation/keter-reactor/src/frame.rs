@@ -0,0 +1,518 @@
+// MIT/Apache2 License
+
+//! A clock for pacing animation frames.
+
+use crate::Timer;
+
+use futures_core::stream::Stream;
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use web_time::{Duration, Instant};
+
+/// What a [`FrameClock`] does when frames are missed because the reactor was busy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissedTickPolicy {
+    /// Skip the missed frames and continue on the original schedule.
+    ///
+    /// The next frame reports how many frames were skipped.
+    #[default]
+    Skip,
+
+    /// Yield all of the missed frames as fast as possible to catch up.
+    Burst,
+
+    /// Yield one frame now and restart the schedule from the current time.
+    Delay,
+}
+
+/// A single frame produced by a [`FrameClock`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    /// The time this frame was scheduled for.
+    time: Instant,
+
+    /// The number of frames skipped before this one.
+    missed: u64,
+}
+
+impl Frame {
+    /// The time this frame was scheduled for.
+    ///
+    /// Animations should use this instead of the current time, so that they advance
+    /// smoothly even if the frame is delivered late.
+    #[inline]
+    pub fn time(&self) -> Instant {
+        self.time
+    }
+
+    /// The number of frames that were skipped right before this one.
+    #[inline]
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+}
+
+/// Statistics on the frames produced by a [`FrameClock`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// The number of frames produced.
+    frames: u64,
+
+    /// The number of frames that were skipped.
+    missed: u64,
+
+    /// The time between the last two frames.
+    last: Duration,
+
+    /// The longest time between two frames.
+    max: Duration,
+
+    /// The total time between frames.
+    total: Duration,
+
+    /// The number of times between frames that were measured.
+    intervals: u32,
+}
+
+impl FrameStats {
+    /// The number of frames produced.
+    #[inline]
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// The number of frames that were skipped.
+    #[inline]
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// The time between the last two frames.
+    #[inline]
+    pub fn last_frame_time(&self) -> Duration {
+        self.last
+    }
+
+    /// The longest time between two frames.
+    #[inline]
+    pub fn max_frame_time(&self) -> Duration {
+        self.max
+    }
+
+    /// The average time between two frames.
+    #[inline]
+    pub fn average_frame_time(&self) -> Duration {
+        match self.intervals {
+            0 => Duration::ZERO,
+            intervals => self.total / intervals,
+        }
+    }
+
+    /// Record a frame that was delivered `frame_time` after the previous one.
+    #[inline]
+    fn record(&mut self, frame_time: Option<Duration>, missed: u64) {
+        self.frames += 1;
+        self.missed += missed;
+
+        if let Some(frame_time) = frame_time {
+            self.last = frame_time;
+            self.max = self.max.max(frame_time);
+            self.total = self.total.saturating_add(frame_time);
+            self.intervals = self.intervals.saturating_add(1);
+        }
+    }
+}
+
+/// The schedule of a [`FrameClock`], independent of any timers.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Schedule {
+    /// The time between frames.
+    period: Duration,
+
+    /// The time the next frame is due.
+    next: Instant,
+
+    /// What to do when frames are missed.
+    policy: MissedTickPolicy,
+}
+
+impl Schedule {
+    /// Create a schedule whose first frame is due at `start`.
+    #[inline]
+    pub(crate) fn new(start: Instant, period: Duration, policy: MissedTickPolicy) -> Self {
+        assert!(!period.is_zero(), "frame period cannot be zero");
+        Self {
+            period,
+            next: start,
+            policy,
+        }
+    }
+
+    /// The time the next frame is due.
+    #[inline]
+    pub(crate) fn next(&self) -> Instant {
+        self.next
+    }
+
+    /// Produce the frame that is due at `now`, or `None` if no frame is due yet.
+    pub(crate) fn tick(&mut self, now: Instant) -> Option<Frame> {
+        let behind = now.checked_duration_since(self.next)?;
+
+        let frame = match self.policy {
+            MissedTickPolicy::Burst => {
+                let frame = Frame {
+                    time: self.next,
+                    missed: 0,
+                };
+                self.next += self.period;
+                frame
+            }
+
+            MissedTickPolicy::Skip => {
+                // Jump to the latest frame that is due.
+                let missed = (behind.as_nanos() / self.period.as_nanos()) as u64;
+                let time = self.next + mul(self.period, missed);
+                self.next = time + self.period;
+                Frame { time, missed }
+            }
+
+            MissedTickPolicy::Delay => {
+                let missed = (behind.as_nanos() / self.period.as_nanos()) as u64;
+                self.next = now + self.period;
+                Frame { time: now, missed }
+            }
+        };
+
+        Some(frame)
+    }
+}
+
+/// Multiply a duration by a possibly large integer, saturating on overflow.
+#[inline]
+fn mul(duration: Duration, n: u64) -> Duration {
+    let nanos = duration.as_nanos().saturating_mul(n as u128);
+    Duration::new(
+        (nanos / 1_000_000_000).min(u64::MAX as u128) as u64,
+        (nanos % 1_000_000_000) as u32,
+    )
+}
+
+/// A stream of frames produced at a target rate.
+///
+/// Unlike [`Timer::interval`], this lets the caller choose what happens when frames are
+/// missed, keeps statistics on frame times, and can be paused when nothing is animating.
+pub struct FrameClock {
+    /// The frame schedule.
+    schedule: Schedule,
+
+    /// The timer for the next frame.
+    timer: Timer,
+
+    /// Whether the clock is paused.
+    paused: bool,
+
+    /// The time the last frame was delivered.
+    last: Option<Instant>,
+
+    /// Statistics on produced frames.
+    stats: FrameStats,
+}
+
+impl fmt::Debug for FrameClock {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameClock")
+            .field("period", &self.schedule.period)
+            .field("policy", &self.schedule.policy)
+            .field("paused", &self.paused)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Unpin for FrameClock {}
+
+impl FrameClock {
+    /// Create a new clock that produces `rate` frames per second, starting now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is zero.
+    #[inline]
+    pub fn new(rate: u32) -> Self {
+        assert_ne!(rate, 0, "frame rate cannot be zero");
+        Self::with_period(Duration::from_secs(1) / rate)
+    }
+
+    /// Create a new clock that produces a frame every `period`, starting now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    #[inline]
+    pub fn with_period(period: Duration) -> Self {
+        let now = crate::time::now();
+        Self {
+            schedule: Schedule::new(now, period, MissedTickPolicy::default()),
+            timer: Timer::at(now),
+            paused: false,
+            last: None,
+            stats: FrameStats::default(),
+        }
+    }
+
+    /// Set what happens when frames are missed.
+    #[inline]
+    pub fn with_missed_tick_policy(mut self, policy: MissedTickPolicy) -> Self {
+        self.set_missed_tick_policy(policy);
+        self
+    }
+
+    /// Set what happens when frames are missed.
+    #[inline]
+    pub fn set_missed_tick_policy(&mut self, policy: MissedTickPolicy) {
+        self.schedule.policy = policy;
+    }
+
+    /// Get the time between frames.
+    #[inline]
+    pub fn period(&self) -> Duration {
+        self.schedule.period
+    }
+
+    /// Change the time between frames.
+    ///
+    /// The next frame is scheduled one new period after the last frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    #[inline]
+    pub fn set_period(&mut self, period: Duration) {
+        let last = self
+            .schedule
+            .next
+            .checked_sub(self.schedule.period)
+            .unwrap_or(self.schedule.next);
+        let start = last + period;
+        self.schedule = Schedule::new(start, period, self.schedule.policy);
+        self.reset_timer();
+    }
+
+    /// Change the number of frames produced per second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is zero.
+    #[inline]
+    pub fn set_rate(&mut self, rate: u32) {
+        assert_ne!(rate, 0, "frame rate cannot be zero");
+        self.set_period(Duration::from_secs(1) / rate);
+    }
+
+    /// Stop producing frames until [`FrameClock::resume`] is called.
+    #[inline]
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.timer.set_never();
+    }
+
+    /// Start producing frames again, with the first one due immediately.
+    #[inline]
+    pub fn resume(&mut self) {
+        if !self.paused {
+            return;
+        }
+
+        let now = crate::time::now();
+        self.paused = false;
+        self.last = None;
+        self.schedule = Schedule::new(now, self.schedule.period, self.schedule.policy);
+        self.reset_timer();
+    }
+
+    /// Tell whether the clock is paused.
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Get statistics on the frames produced so far.
+    #[inline]
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    /// Clear the statistics on the frames produced so far.
+    #[inline]
+    pub fn reset_stats(&mut self) {
+        self.stats = FrameStats::default();
+    }
+
+    /// Point the timer at the next frame.
+    #[inline]
+    fn reset_timer(&mut self) {
+        if !self.paused {
+            self.timer.set_at(self.schedule.next());
+        }
+    }
+}
+
+impl Stream for FrameClock {
+    type Item = Frame;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.paused {
+            return Poll::Pending;
+        }
+
+        loop {
+            futures_lite::ready!(Pin::new(&mut this.timer).poll(cx));
+
+            // Wait longer if the timer fired before the frame was due.
+            let now = crate::time::now();
+            let Some(frame) = this.schedule.tick(now) else {
+                this.reset_timer();
+                continue;
+            };
+            this.reset_timer();
+
+            let frame_time = this.last.map(|last| now.saturating_duration_since(last));
+            this.last = Some(now);
+            this.stats.record(frame_time, frame.missed);
+
+            return Poll::Ready(Some(frame));
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // This stream runs forever.
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_schedule() {
+        let period = Duration::from_millis(10);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // Frames are not produced early.
+        let mut schedule = Schedule::new(at(10), period, MissedTickPolicy::Skip);
+        assert!(schedule.tick(at(5)).is_none());
+
+        // Skipping keeps the original phase and reports missed frames.
+        let frame = schedule.tick(at(10)).unwrap();
+        assert_eq!((frame.time(), frame.missed()), (at(10), 0));
+        let frame = schedule.tick(at(45)).unwrap();
+        assert_eq!((frame.time(), frame.missed()), (at(40), 2));
+        assert_eq!(schedule.next(), at(50));
+
+        // Bursting produces every missed frame.
+        let mut schedule = Schedule::new(at(10), period, MissedTickPolicy::Burst);
+        let times = (0..4)
+            .map(|_| schedule.tick(at(45)).unwrap().time())
+            .collect::<Vec<_>>();
+        assert_eq!(times, [at(10), at(20), at(30), at(40)]);
+        assert!(schedule.tick(at(45)).is_none());
+
+        // Delaying restarts the schedule from the late frame.
+        let mut schedule = Schedule::new(at(10), period, MissedTickPolicy::Delay);
+        let frame = schedule.tick(at(45)).unwrap();
+        assert_eq!((frame.time(), frame.missed()), (at(45), 3));
+        assert_eq!(schedule.next(), at(55));
+    }
+
+    #[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+    #[test]
+    fn frame_clock() {
+        use crate::test_reactor;
+        use crate::time::{self, VirtualClock};
+        use futures_lite::{future, StreamExt};
+
+        /// Get the frame that is ready right now, as its time and missed count.
+        async fn ready(frames: &mut FrameClock) -> Option<(Instant, u64)> {
+            future::poll_once(frames.next())
+                .await
+                .flatten()
+                .map(|frame| (frame.time(), frame.missed()))
+        }
+
+        for policy in [
+            MissedTickPolicy::Skip,
+            MissedTickPolicy::Burst,
+            MissedTickPolicy::Delay,
+        ] {
+            let clock = VirtualClock::new();
+            let reactor = test_reactor(|builder| builder.virtual_clock(clock.clone()));
+
+            reactor
+                .__block_on_result(async {
+                    let start = time::now();
+                    let at = |millis| start + Duration::from_millis(millis);
+                    let mut frames = FrameClock::new(100).with_missed_tick_policy(policy);
+                    assert_eq!(frames.period(), Duration::from_millis(10));
+
+                    // Frames arrive exactly on schedule.
+                    assert_eq!(ready(&mut frames).await, Some((at(0), 0)));
+                    assert_eq!(ready(&mut frames).await, None);
+                    clock.advance(Duration::from_millis(9));
+                    assert_eq!(ready(&mut frames).await, None);
+                    clock.advance(Duration::from_millis(1));
+                    assert_eq!(ready(&mut frames).await, Some((at(10), 0)));
+                    assert_eq!(frames.stats().last_frame_time(), Duration::from_millis(10));
+
+                    // Stall for three and a half frames.
+                    clock.advance(Duration::from_millis(35));
+                    let mut stalled = Vec::new();
+                    while let Some(frame) = ready(&mut frames).await {
+                        stalled.push(frame);
+                    }
+
+                    let (expected, next, missed): (&[_], _, _) = match policy {
+                        MissedTickPolicy::Skip => (&[(at(40), 2)], at(50), 2),
+                        MissedTickPolicy::Burst => {
+                            (&[(at(20), 0), (at(30), 0), (at(40), 0)], at(50), 0)
+                        }
+                        MissedTickPolicy::Delay => (&[(at(45), 2)], at(55), 2),
+                    };
+                    assert_eq!(stalled, expected);
+
+                    // The schedule continues from where the policy left it.
+                    clock.advance(next.duration_since(time::now()) - Duration::from_millis(1));
+                    assert_eq!(ready(&mut frames).await, None);
+                    clock.advance(Duration::from_millis(1));
+                    assert_eq!(ready(&mut frames).await, Some((next, 0)));
+
+                    let stats = frames.stats();
+                    assert_eq!(stats.frames(), 3 + expected.len() as u64);
+                    assert_eq!(stats.missed(), missed);
+                    assert_eq!(stats.max_frame_time(), Duration::from_millis(35));
+                    assert_eq!(stats.last_frame_time(), next.duration_since(at(45)));
+
+                    // Paused clocks produce no frames, and resume right away.
+                    frames.pause();
+                    clock.advance(Duration::from_millis(100));
+                    assert_eq!(ready(&mut frames).await, None);
+                    frames.resume();
+                    assert_eq!(
+                        ready(&mut frames).await,
+                        Some((next + Duration::from_millis(100), 0))
+                    );
+                    assert_eq!(frames.stats().frames(), 4 + expected.len() as u64);
+                    Ok(())
+                })
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,543 @@
+// MIT/Apache2 License
+
+//! Callbacks that run when the reactor is about to go to sleep.
+
+use crate::Timer;
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+use web_time::{Duration, Instant};
+
+/// The longest time budget given to idle callbacks in one idle period.
+const MAX_IDLE_BUDGET: Duration = Duration::from_millis(50);
+
+thread_local! {
+    /// The idle queues of the reactors running on this thread, innermost last.
+    static CURRENT: RefCell<Vec<Rc<IdleQueue>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Get the idle queue of the innermost reactor on this thread.
+#[inline]
+fn current() -> Rc<IdleQueue> {
+    CURRENT
+        .with(|current| current.borrow().last().cloned())
+        .expect("idle callbacks can only be used inside of a running reactor")
+}
+
+/// A hook that runs before the reactor sleeps.
+type SleepHook = Rc<RefCell<dyn FnMut()>>;
+
+/// A callback that runs once the reactor is idle.
+type IdleCallback = Box<dyn FnOnce(IdleDeadline)>;
+
+/// A source of events that is checked around the reactor's sleep.
+type Source = Rc<RefCell<dyn SleepSource>>;
+
+/// Something that has to run right after the reactor wakes up and right before it sleeps.
+pub(crate) trait SleepSource {
+    /// Called every time the reactor is polled, before the reactor's future.
+    fn after_wake(&mut self, cx: &mut Context<'_>);
+
+    /// Called right before the reactor goes to sleep, after everything else has run.
+    ///
+    /// The reactor does not go to sleep if this wakes up `cx`.
+    fn before_sleep(&mut self, cx: &mut Context<'_>);
+}
+
+/// The idle state of a single reactor run.
+pub(crate) struct IdleQueue {
+    /// How long the reactor must expect to sleep for to be idle.
+    threshold: Duration,
+
+    /// Hooks to run before sleeping, along with their IDs.
+    before_sleep: RefCell<Vec<(usize, SleepHook)>>,
+
+    /// Sources to check around sleeping, along with their IDs.
+    sources: RefCell<Vec<(usize, Source)>>,
+
+    /// The ID to use for the next hook.
+    next_id: Cell<usize>,
+
+    /// The number of times the reactor has gone idle.
+    generation: Cell<u64>,
+
+    /// Tasks waiting for the reactor to go idle.
+    waiters: RefCell<Vec<Waker>>,
+
+    /// Callbacks waiting for the reactor to go idle, with their deadlines.
+    callbacks: RefCell<Vec<(Option<Instant>, IdleCallback)>>,
+
+    /// Timer for the earliest callback deadline, along with that deadline.
+    timer: RefCell<(Timer, Option<Instant>)>,
+
+    /// Waker used to detect whether the reactor was woken while polling.
+    flag: RefCell<Option<Arc<WakeFlag>>>,
+}
+
+impl IdleQueue {
+    /// Create the idle state for a new reactor run.
+    #[inline]
+    pub(crate) fn new(threshold: Duration) -> Rc<Self> {
+        Rc::new(IdleQueue {
+            threshold,
+            before_sleep: RefCell::new(Vec::new()),
+            sources: RefCell::new(Vec::new()),
+            next_id: Cell::new(0),
+            generation: Cell::new(0),
+            waiters: RefCell::new(Vec::new()),
+            callbacks: RefCell::new(Vec::new()),
+            timer: RefCell::new((Timer::never(), None)),
+            flag: RefCell::new(None),
+        })
+    }
+
+    /// Use this as the idle state for this thread until the guard is dropped.
+    #[inline]
+    pub(crate) fn enter(self: &Rc<Self>) -> IdleScope {
+        CURRENT.with(|current| current.borrow_mut().push(self.clone()));
+        IdleScope(())
+    }
+
+    /// Poll the reactor's future, running idle callbacks if it is about to sleep.
+    pub(crate) fn poll<F: Future>(
+        &self,
+        future: Pin<&mut F>,
+        cx: &mut Context<'_>,
+    ) -> Poll<F::Output> {
+        // Handle the events that came in while the reactor was asleep.
+        for source in self.sources() {
+            source.borrow_mut().after_wake(cx);
+        }
+
+        // Run any callbacks that have passed their deadline.
+        self.run_overdue(cx);
+
+        // Poll the future with a waker that tells us if it was woken.
+        let flag = self.flag(cx.waker());
+        flag.woken.store(false, Ordering::SeqCst);
+        let waker = Waker::from(flag.clone());
+        let poll = future.poll(&mut Context::from_waker(&waker));
+
+        if poll.is_pending() && !flag.woken.load(Ordering::SeqCst) {
+            // Nothing is ready to run, so the reactor is about to sleep.
+            self.before_sleep(&mut Context::from_waker(&waker));
+
+            // In a simulation, deliver the next wakeup instead of sleeping. If there is none
+            // and time is virtual, skip ahead to the next timer.
+            if !flag.woken.load(Ordering::SeqCst)
+                && !crate::sim::wake_next()
+                && crate::time::auto_advance()
+            {
+                crate::sim::wake_next();
+            }
+        }
+
+        poll
+    }
+}
+
+/// Stops using an [`IdleQueue`] as the idle state for this thread when dropped.
+pub(crate) struct IdleScope(());
+
+impl Drop for IdleScope {
+    #[inline]
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.borrow_mut().pop());
+    }
+}
+
+impl IdleQueue {
+    /// Get the flag waker that wraps the given waker.
+    fn flag(&self, waker: &Waker) -> Arc<WakeFlag> {
+        let mut flag = self.flag.borrow_mut();
+        match &*flag {
+            Some(flag) if flag.waker.lock().unwrap().will_wake(waker) => {}
+            _ => {
+                *flag = Some(Arc::new(WakeFlag {
+                    woken: AtomicBool::new(false),
+                    waker: Mutex::new(waker.clone()),
+                }))
+            }
+        }
+
+        flag.clone().unwrap()
+    }
+
+    /// Run callbacks whose deadlines have passed, and set the timer for the next one.
+    fn run_overdue(&self, cx: &mut Context<'_>) {
+        let now = crate::time::now();
+        let overdue = {
+            let mut callbacks = self.callbacks.borrow_mut();
+            let (overdue, pending) = mem::take(&mut *callbacks)
+                .into_iter()
+                .partition::<Vec<_>, _>(|(deadline, _)| deadline.is_some_and(|d| d <= now));
+            *callbacks = pending;
+            overdue
+        };
+
+        for (_, callback) in overdue {
+            callback(IdleDeadline {
+                end: now,
+                did_timeout: true,
+            });
+        }
+
+        // Wake up at the next deadline.
+        let next = self
+            .callbacks
+            .borrow()
+            .iter()
+            .filter_map(|(deadline, _)| *deadline)
+            .min();
+        let mut timer = self.timer.borrow_mut();
+        let (timer, deadline) = &mut *timer;
+        if *deadline != next {
+            *deadline = next;
+            match next {
+                Some(next) => timer.set_at(next),
+                None => timer.set_never(),
+            }
+        }
+        if Pin::new(timer).poll(cx).is_ready() {
+            cx.waker().wake_by_ref();
+        }
+    }
+
+    /// Run the idle machinery before the reactor goes to sleep.
+    fn before_sleep(&self, cx: &mut Context<'_>) {
+        // Tell how long we expect to sleep for.
+        let now = crate::time::now();
+        let sleep =
+            crate::time::next_deadline().map(|deadline| deadline.saturating_duration_since(now));
+
+        if sleep.is_none_or(|sleep| sleep >= self.threshold) {
+            // Wake up tasks waiting for the reactor to go idle.
+            self.generation.set(self.generation.get() + 1);
+            let waiters = mem::take(&mut *self.waiters.borrow_mut());
+            if !waiters.is_empty() {
+                // The woken tasks run before we sleep, so wait until they are done.
+                waiters.into_iter().for_each(Waker::wake);
+                return;
+            }
+
+            // Run idle callbacks until the budget runs out.
+            let budget = sleep.map_or(MAX_IDLE_BUDGET, |sleep| sleep.min(MAX_IDLE_BUDGET));
+            let deadline = IdleDeadline {
+                end: now + budget,
+                did_timeout: false,
+            };
+            loop {
+                if deadline.time_remaining().is_zero() {
+                    break;
+                }
+
+                let callback = {
+                    let mut callbacks = self.callbacks.borrow_mut();
+                    if callbacks.is_empty() {
+                        break;
+                    }
+                    callbacks.remove(0).1
+                };
+                callback(deadline);
+            }
+        }
+
+        // Run the hooks registered for right before sleeping.
+        let hooks = self
+            .before_sleep
+            .borrow()
+            .iter()
+            .map(|(_, hook)| hook.clone())
+            .collect::<Vec<_>>();
+        for hook in hooks {
+            (hook.borrow_mut())();
+        }
+
+        // Sources go last, so they see everything the hooks did.
+        for source in self.sources() {
+            source.borrow_mut().before_sleep(cx);
+        }
+    }
+
+    /// Get the registered sources.
+    #[inline]
+    fn sources(&self) -> Vec<Source> {
+        self.sources
+            .borrow()
+            .iter()
+            .map(|(_, source)| source.clone())
+            .collect()
+    }
+}
+
+/// A waker that records whether it was woken.
+struct WakeFlag {
+    /// Whether the waker was woken.
+    woken: AtomicBool,
+
+    /// The waker to forward wakeups to.
+    waker: Mutex<Waker>,
+}
+
+impl Wake for WakeFlag {
+    #[inline]
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    #[inline]
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::SeqCst);
+        self.waker.lock().unwrap().wake_by_ref();
+    }
+}
+
+/// Wait until the reactor running on this thread is idle.
+///
+/// The reactor is idle when it has nothing else to do and is about to go to sleep. This is
+/// useful for batching work, like layout and painting, that should happen once all pending
+/// events have been handled.
+///
+/// Waiting for the reactor to go idle counts as work, so waiting for it again right after
+/// it completes keeps the reactor from sleeping.
+///
+/// # Panics
+///
+/// Panics if polled outside of a running reactor.
+#[inline]
+pub fn idle() -> Idle {
+    Idle { generation: None }
+}
+
+/// The future returned by [`idle`].
+#[must_use = "futures do nothing unless polled"]
+pub struct Idle {
+    /// The idle generation of the reactor when this was first polled.
+    generation: Option<u64>,
+}
+
+impl fmt::Debug for Idle {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Idle").finish_non_exhaustive()
+    }
+}
+
+impl Future for Idle {
+    type Output = ();
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let queue = current();
+        let current = queue.generation.get();
+
+        match self.generation {
+            Some(generation) if generation != current => Poll::Ready(()),
+            _ => {
+                self.generation = Some(current);
+                queue.waiters.borrow_mut().push(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Run a hook every time the reactor running on this thread is about to go to sleep.
+///
+/// This runs once per iteration of the reactor's loop, after all pending work has been
+/// handled. The hook is removed once the returned handle is dropped.
+///
+/// # Panics
+///
+/// Panics if no reactor is running on this thread.
+#[inline]
+pub fn on_before_sleep(hook: impl FnMut() + 'static) -> BeforeSleep {
+    let queue = current();
+    let id = queue.next_id.get();
+    queue.next_id.set(id + 1);
+    queue
+        .before_sleep
+        .borrow_mut()
+        .push((id, Rc::new(RefCell::new(hook))));
+
+    BeforeSleep {
+        queue: Rc::downgrade(&queue),
+        id,
+    }
+}
+
+/// A handle to a hook registered with [`on_before_sleep`].
+///
+/// Dropping this handle removes the hook. Use [`BeforeSleep::detach`] to keep it for as
+/// long as the reactor runs instead.
+#[must_use = "dropping a `BeforeSleep` removes the hook"]
+pub struct BeforeSleep {
+    /// The queue the hook is registered in.
+    queue: std::rc::Weak<IdleQueue>,
+
+    /// The ID of the hook.
+    id: usize,
+}
+
+impl fmt::Debug for BeforeSleep {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BeforeSleep")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BeforeSleep {
+    /// Keep the hook for as long as the reactor runs.
+    #[inline]
+    pub fn detach(self) {
+        mem::forget(self);
+    }
+}
+
+impl Drop for BeforeSleep {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.upgrade() {
+            queue
+                .before_sleep
+                .borrow_mut()
+                .retain(|(id, _)| *id != self.id);
+        }
+    }
+}
+
+/// Check a source around the sleep of the reactor running on this thread.
+///
+/// The source is removed once the returned guard is dropped.
+///
+/// # Panics
+///
+/// Panics if no reactor is running on this thread.
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+#[inline]
+pub(crate) fn add_source(source: Source) -> SourceGuard {
+    let queue = current();
+    let id = queue.next_id.get();
+    queue.next_id.set(id + 1);
+    queue.sources.borrow_mut().push((id, source));
+
+    SourceGuard {
+        queue: Rc::downgrade(&queue),
+        id,
+    }
+}
+
+/// Removes a source added with [`add_source`] when dropped.
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+pub(crate) struct SourceGuard {
+    /// The queue the source is registered in.
+    queue: std::rc::Weak<IdleQueue>,
+
+    /// The ID of the source.
+    id: usize,
+}
+
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+impl Drop for SourceGuard {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.upgrade() {
+            queue.sources.borrow_mut().retain(|(id, _)| *id != self.id);
+        }
+    }
+}
+
+/// Run a callback once the reactor running on this thread is idle.
+///
+/// Callbacks run in the order they were requested. They share a time budget for each idle
+/// period, which is passed to them as an [`IdleDeadline`]; callbacks that do not fit into
+/// the budget wait for the next idle period. If `deadline` passes before the reactor goes
+/// idle, the callback runs anyway.
+///
+/// # Panics
+///
+/// Panics if no reactor is running on this thread.
+#[inline]
+pub fn request_idle_callback(
+    deadline: Option<Instant>,
+    callback: impl FnOnce(IdleDeadline) + 'static,
+) {
+    let queue = current();
+    queue
+        .callbacks
+        .borrow_mut()
+        .push((deadline, Box::new(callback)));
+
+    // Make sure the reactor notices the new deadline.
+    if deadline.is_some() {
+        if let Some(flag) = &*queue.flag.borrow() {
+            flag.wake_by_ref();
+        }
+    }
+}
+
+/// The time budget given to an idle callback.
+#[derive(Debug, Clone, Copy)]
+pub struct IdleDeadline {
+    /// The end of the budget.
+    end: Instant,
+
+    /// Whether the callback is running because its deadline passed.
+    did_timeout: bool,
+}
+
+impl IdleDeadline {
+    /// Get how much time is left in the budget.
+    #[inline]
+    pub fn time_remaining(&self) -> Duration {
+        self.end.saturating_duration_since(crate::time::now())
+    }
+
+    /// Tell whether the callback is running because its deadline passed, rather than
+    /// because the reactor is idle.
+    #[inline]
+    pub fn did_timeout(&self) -> bool {
+        self.did_timeout
+    }
+}
+
+#[cfg(all(test, unix, not(target_vendor = "apple"), not(target_os = "android")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_threshold() {
+        use crate::{spawn_local, test_reactor};
+
+        let reactor = test_reactor(|builder| builder.idle_threshold(Duration::from_secs(10)));
+
+        reactor
+            .__block_on_result(async {
+                // A timer due sooner than the threshold keeps the reactor busy.
+                let fired = Rc::new(Cell::new(false));
+                let task = spawn_local({
+                    let fired = fired.clone();
+                    async move {
+                        Timer::after(Duration::from_millis(20)).await;
+                        fired.set(true);
+                    }
+                });
+
+                idle().await;
+                assert!(fired.get());
+                task.await;
+                Ok(())
+            })
+            .unwrap();
+    }
+}
@@ -4,22 +4,58 @@
 
 #![forbid(unsafe_code)]
 
+// Allow the procedural macros to refer to this crate by name.
+extern crate self as keter_reactor;
+
+mod builder;
+mod executor;
+mod exit;
+mod frame;
+mod idle;
 pub mod platform;
+mod proxy;
+mod shutdown;
+pub mod sim;
 mod sys;
+pub mod time;
+mod timer_queue;
 
+use std::cell::Cell;
 use std::convert::Infallible;
 use std::fmt;
 use std::future::Future;
 use std::io;
 use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
 use std::task::{Context, Poll};
 
 use futures_core::stream::Stream;
-use web_time::{Duration, Instant};
+use web_time::{Duration, Instant, SystemTime};
 
+pub use builder::{Instrument, ReactorBuilder};
+pub use exit::ExitHandle;
+pub use frame::{Frame, FrameClock, FrameStats, MissedTickPolicy};
+pub use idle::{idle, on_before_sleep, request_idle_callback, BeforeSleep, Idle, IdleDeadline};
+pub use keter_reactor_macros::test;
+pub use proxy::{Messages, Proxy, SendError};
 pub use web_time;
 
+/// Attributes for creating entry points and tests.
+///
+/// The [`main!`] macro already takes the name `main` at the crate root, so the `main`
+/// attribute lives here. [`test`] is also exported at the crate root.
+///
+/// [`main!`]: crate::main!
+pub mod attr {
+    pub use keter_reactor_macros::{main, test};
+}
+
 /// Macro for creating the main function.
+///
+/// If the body returns an error, it is printed and the process exits with a non-zero exit
+/// code. Otherwise, the process exits with the code passed to [`exit_with`]. The
+/// [`attr::main`] attribute expands to this macro.
 #[macro_export]
 macro_rules! main {
     (
@@ -42,8 +78,11 @@ macro_rules! main {
             // Run the block to get a result.
             let result = $bl;
 
-            // TODO: handle result.
-            let _ = result.unwrap();
+            // Exit with the code from the result.
+            let code = $crate::__report(result);
+            if code != 0 {
+                ::std::process::exit(code);
+            }
         }
 
         #[cfg(target_os = "android")]
@@ -56,12 +95,33 @@ macro_rules! main {
             // Run the block to get a result.
             let result = $bl;
 
-            // TODO: Handle the result properly.
-            let _ = result;
+            // Android apps have no exit code, so just report errors.
+            let _ = $crate::__report(result);
         }
     };
 }
 
+/// Report the result of `main` and get the exit code for it.
+///
+/// This function is not meant to be used in the public API.
+#[doc(hidden)]
+pub fn __report(result: Main) -> i32 {
+    match result {
+        Ok(finished) => finished.exit_code(),
+        Err(err) => {
+            eprintln!("Error: {err}");
+
+            let mut source = std::error::Error::source(&err);
+            while let Some(err) = source {
+                eprintln!("  caused by: {err}");
+                source = err.source();
+            }
+
+            1
+        }
+    }
+}
+
 /// The type intended to be returned from `main`.
 pub type Main = Result<Finished>;
 
@@ -71,59 +131,425 @@ pub use io::Result;
 
 /// The type produced by a finished application.
 pub struct Finished {
-    _private: (),
+    /// The exit code requested by the application.
+    exit_code: i32,
 }
 
 impl fmt::Debug for Finished {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Finished").finish_non_exhaustive()
+        f.debug_struct("Finished")
+            .field("exit_code", &self.exit_code)
+            .finish()
     }
 }
 
 impl Finished {
-    fn new() -> Self {
-        Self { _private: () }
+    fn new(exit_code: i32) -> Self {
+        Self { exit_code }
+    }
+
+    /// The exit code requested through [`exit_with`].
+    ///
+    /// This is zero if the application exited through [`exit`].
+    #[inline]
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code
+    }
+
+    /// Tell whether the application requested a successful exit.
+    #[inline]
+    pub fn is_success(&self) -> bool {
+        self.exit_code == 0
     }
 }
 
 /// Settings for the reactor to drive the system.
 pub struct Reactor {
+    config: builder::Config,
     settings: sys::Settings,
+    posted: proxy::PostQueue,
+    exit: Arc<exit::Signal>,
 }
 
 impl fmt::Debug for Reactor {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Reactor").finish_non_exhaustive()
+        f.debug_struct("Reactor")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
     }
 }
 
 impl Reactor {
+    /// Create a new reactor with the given settings.
+    #[inline]
+    fn with_settings(config: builder::Config, settings: sys::Settings) -> Self {
+        Self {
+            config,
+            settings,
+            posted: proxy::PostQueue::new(),
+            exit: Arc::new(exit::Signal::new()),
+        }
+    }
+
     /// Block on a future for as long as possible.
+    ///
+    /// The reactor can be run again once this returns. Running a reactor while it is
+    /// already running returns an error. Running a different reactor inside of this one
+    /// blocks this one until the inner reactor exits.
+    #[inline]
+    pub fn block_on(&self, future: impl Future<Output = Infallible>) -> Result<Finished> {
+        match self.run(future)? {
+            Ok(infall) => match infall {},
+            Err(exit_code) => Ok(Finished::new(exit_code)),
+        }
+    }
+
+    /// Block on a future that returns a result, exiting once it completes.
+    ///
+    /// This function is not meant to be used in the public API.
+    #[doc(hidden)]
     #[inline]
-    pub fn block_on(self, future: impl Future<Output = Infallible>) -> Result<Finished> {
-        if let Some(infall) = sys::block_on(self.settings, future)? {
-            match infall {}
+    pub fn __block_on_result(&self, future: impl Future<Output = Result<()>>) -> Result<Finished> {
+        match self.run(future)? {
+            Ok(result) => result.map(|()| Finished::new(0)),
+            Err(exit_code) => Ok(Finished::new(exit_code)),
         }
-        Ok(Finished::new())
+    }
+
+    /// Run a future to completion, or get the exit code if it exits early.
+    fn run<T>(&self, future: impl Future<Output = T>) -> Result<std::result::Result<T, i32>> {
+        // Make this the current reactor on this thread.
+        let run = Run::start(self)?;
+        let _scope = run.enter();
+
+        // The future may be large, so keep it off of the stack while it is wrapped.
+        let future = run.wrap(Box::pin(future));
+        let result = sys::block_on(&self.settings, &self.config, &self.exit, future);
+
+        if let Ok(Err(exit_code)) = &result {
+            run.set_exit_code(*exit_code);
+        }
+        result
+    }
+
+    /// Get the name of this reactor, if it was given one.
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.config.name.as_deref()
+    }
+
+    /// Get a handle that can be used to make this reactor exit from any thread.
+    #[inline]
+    pub fn exit_handle(&self) -> ExitHandle {
+        ExitHandle(self.exit.clone())
+    }
+
+    /// Set how long the hooks registered with [`on_exit`] are given to run.
+    ///
+    /// Hooks that are still running once the deadline passes are dropped. The default is
+    /// five seconds.
+    #[inline]
+    pub fn with_exit_deadline(mut self, deadline: Duration) -> Self {
+        self.config.exit_deadline = deadline;
+        self
+    }
+
+    /// Create a [`Proxy`] for sending work into this reactor from other threads.
+    ///
+    /// Messages sent through the [`Proxy`] are received by the returned [`Messages`]
+    /// stream.
+    #[inline]
+    pub fn proxy<T: Send + 'static>(&self) -> (Proxy<T>, Messages<T>) {
+        self.posted.proxy()
     }
 }
 
-/// Indicate to the reactor that we want to exit as soon as possible.
+/// The state of a single run of a [`Reactor`].
+pub(crate) struct Run<'a> {
+    /// The reactor being run.
+    reactor: &'a Reactor,
+
+    /// Marks the reactor as running.
+    running: exit::RunGuard,
+
+    /// Cleanup hooks registered during this run.
+    hooks: shutdown::HookFrame,
+
+    /// The idle state of this run.
+    idle: Rc<idle::IdleQueue>,
+
+    /// The exit code the run ended with, if it was told to exit.
+    exit_code: Cell<Option<i32>>,
+}
+
+impl<'a> Run<'a> {
+    /// Start running a reactor.
+    ///
+    /// Fails if the reactor is already running.
+    pub(crate) fn start(reactor: &'a Reactor) -> Result<Self> {
+        let run = Self {
+            reactor,
+            running: reactor.exit.start()?,
+            hooks: shutdown::HookFrame::new(),
+            idle: idle::IdleQueue::new(reactor.config.idle_threshold),
+            exit_code: Cell::new(None),
+        };
+
+        let name = reactor.config.name.as_deref();
+        for instrument in &reactor.config.instruments {
+            instrument.on_start(name);
+        }
+
+        Ok(run)
+    }
+
+    /// Make this the innermost reactor running on this thread until the scope is dropped.
+    pub(crate) fn enter(&self) -> impl Sized + '_ {
+        let config = &self.reactor.config;
+        (
+            self.running.enter(),
+            self.hooks.enter(),
+            SlackScope::enter(config.timer_slack),
+            time::ClockScope::enter(config.clock.clone()),
+            sim::SimScope::enter(config.simulation.clone()),
+            self.idle.enter(),
+        )
+    }
+
+    /// Run spawned tasks, closures posted by proxies and idle callbacks alongside a future.
+    ///
+    /// The returned future must be polled inside of [`Run::enter`].
+    pub(crate) fn wrap<'f, T: 'f>(
+        &self,
+        future: impl Future<Output = T> + 'f,
+    ) -> impl Future<Output = T> + 'f
+    where
+        'a: 'f,
+    {
+        let reactor = self.reactor;
+        let idle = self.idle.clone();
+
+        let future = executor::run(async move {
+            let posted = async { match reactor.posted.run().await {} };
+            futures_lite::future::or(future, posted).await
+        });
+
+        // Run idle callbacks before sleeping, and measure how long each poll takes if
+        // anyone is listening.
+        async move {
+            let name = reactor.config.name.as_deref();
+            let instruments = &reactor.config.instruments;
+            let mut future = std::pin::pin!(future);
+
+            futures_lite::future::poll_fn(|cx| {
+                if instruments.is_empty() {
+                    return idle.poll(future.as_mut(), cx);
+                }
+
+                let start = Instant::now();
+                let poll = idle.poll(future.as_mut(), cx);
+                let busy = start.elapsed();
+                for instrument in instruments {
+                    instrument.on_poll(name, busy);
+                }
+                poll
+            })
+            .await
+        }
+    }
+
+    /// Record the exit code the run ended with.
+    #[inline]
+    pub(crate) fn set_exit_code(&self, exit_code: i32) {
+        self.exit_code.set(Some(exit_code));
+    }
+}
+
+impl Drop for Run<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        let name = self.reactor.config.name.as_deref();
+        for instrument in &self.reactor.config.instruments {
+            instrument.on_stop(name, self.exit_code.get());
+        }
+    }
+}
+
+/// Indicate to the reactor running this future that we want to exit as soon as possible.
+///
+/// This is equivalent to `exit_with(0)`.
+///
+/// # Panics
+///
+/// Panics if no reactor is running on this thread.
 #[cold]
 pub async fn exit() -> ! {
-    sys::exit().expect("failed to exit the program");
+    exit_with(0).await
+}
+
+/// Indicate to the reactor running this future that we want to exit as soon as possible
+/// with an exit code.
+///
+/// If reactors are nested on this thread, the innermost one exits. The exit code is
+/// recorded in the [`Finished`] returned by [`Reactor::block_on`]. Use
+/// [`Reactor::exit_handle`] to make a specific reactor exit.
+///
+/// # Panics
+///
+/// Panics if no reactor is running on this thread.
+#[cold]
+pub async fn exit_with(code: i32) -> ! {
+    exit::current()
+        .expect("`exit` was called outside of a running reactor")
+        .stop(code);
     std::future::pending().await
 }
 
+/// Register a future to run when the innermost reactor running on this thread stops.
+///
+/// Once the reactor stops, either through [`exit`] or because the future passed to it has
+/// completed, all registered hooks are run concurrently before [`Reactor::block_on`]
+/// returns. This gives the application a chance to flush files, save state or close
+/// connections. Hooks are only given a limited amount of time to run; see
+/// [`Reactor::with_exit_deadline`].
+///
+/// Hooks registered while no reactor is running are run when the next reactor on this
+/// thread stops.
+#[inline]
+pub fn on_exit(hook: impl Future<Output = ()> + 'static) {
+    shutdown::register(hook);
+}
+
+/// Spawn a task onto the reactor running on the current thread.
+///
+/// The task runs concurrently with the future passed to [`Reactor::block_on`]. If the
+/// reactor is not running yet, the task will start running once it is.
+#[inline]
+pub fn spawn_local<T: 'static>(future: impl Future<Output = T> + 'static) -> JoinHandle<T> {
+    JoinHandle(executor::spawn_local(future))
+}
+
+/// Spawn a task that can be run by any reactor.
+///
+/// This can be called from any thread. The task is run by the next reactor that polls
+/// for tasks.
+#[inline]
+pub fn spawn<T: Send + 'static>(future: impl Future<Output = T> + Send + 'static) -> JoinHandle<T> {
+    JoinHandle(executor::spawn(future))
+}
+
+/// A handle to a spawned task.
+///
+/// Dropping this handle cancels the task. Use [`JoinHandle::detach`] to let it run in the
+/// background instead.
+#[must_use = "dropping a `JoinHandle` cancels the task"]
+pub struct JoinHandle<T>(executor::Task<T>);
+
+impl<T> fmt::Debug for JoinHandle<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinHandle")
+            .field("finished", &self.0.is_finished())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> JoinHandle<T> {
+    /// Let the task run in the background without waiting for its output.
+    #[inline]
+    pub fn detach(self) {
+        self.0.detach();
+    }
+
+    /// Cancel the task and wait for it to stop running.
+    ///
+    /// Returns the task's output if it finished before it was cancelled.
+    #[inline]
+    pub async fn cancel(self) -> Option<T> {
+        self.0.cancel().await
+    }
+
+    /// Tell whether the task has finished running.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
+
 /// A timer that waits for a specific amount of time in the run loop.
-pub struct Timer(sys::Timer);
+///
+/// Timers created while a reactor with a [`VirtualClock`] is running follow that clock
+/// instead of the real time.
+///
+/// By default, timers run against the monotonic clock, which does not count time spent
+/// suspended. Use [`Timer::never_on`] or [`Timer::at_system_time`] to run a timer against
+/// another [`TimerClock`].
+///
+/// [`VirtualClock`]: time::VirtualClock
+pub struct Timer {
+    /// The underlying timer.
+    kind: TimerKind,
+
+    /// The delay the timer was last armed with, used by [`Timer::reset`].
+    delay: Option<Duration>,
+}
+
+/// The clock that a [`Timer`] runs against.
+enum TimerKind {
+    /// The real time, using the platform's timers.
+    Sys(sys::Timer),
+
+    /// A virtual clock.
+    Virtual(time::VirtualTimer),
+
+    /// A clock other than the monotonic clock.
+    #[cfg(target_os = "linux")]
+    Clock(sys::ClockTimer),
+}
+
+/// A clock that a [`Timer`] can run against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum TimerClock {
+    /// A clock that only moves forward, and stops while the system is suspended.
+    ///
+    /// This is the clock used by [`Instant`].
+    #[default]
+    Monotonic,
+
+    /// A clock that only moves forward, and keeps counting while the system is suspended.
+    ///
+    /// Timers on this clock are useful for work that should happen after some amount of
+    /// real time has passed, like refreshing data every five minutes.
+    Boot,
+
+    /// The wall clock, which follows changes to the system time.
+    ///
+    /// Timers on this clock are useful for work that should happen at a time of day, like
+    /// a reminder at 09:00. They fire at the right time even if the system time is changed
+    /// while they are waiting.
+    Wall,
+}
 
 impl fmt::Debug for Timer {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Timer").finish_non_exhaustive()
+        f.debug_struct("Timer")
+            .field("clock", &self.clock())
+            .field("deadline", &self.deadline())
+            .field("period", &self.period())
+            .finish_non_exhaustive()
     }
 }
 
@@ -133,62 +559,137 @@ impl Timer {
     /// Create a new timer that never fires.
     #[inline]
     pub fn never() -> Self {
-        Self(sys::Timer::never())
+        let kind = match time::current_clock() {
+            Some(clock) => TimerKind::Virtual(time::VirtualTimer::new(clock)),
+            None => TimerKind::Sys(sys::Timer::never()),
+        };
+
+        Self { kind, delay: None }
+    }
+
+    /// Create a new timer on the given clock that never fires.
+    ///
+    /// The timer keeps running against this clock when it is armed with any of the other
+    /// methods, with deadlines given as an [`Instant`] converted to the clock when they are
+    /// set.
+    ///
+    /// Only Linux supports clocks other than [`TimerClock::Monotonic`]. Elsewhere, and
+    /// while a reactor with a [`VirtualClock`] is running, this creates a timer on the
+    /// monotonic clock.
+    ///
+    /// [`VirtualClock`]: time::VirtualClock
+    #[inline]
+    pub fn never_on(clock: TimerClock) -> Self {
+        #[cfg(target_os = "linux")]
+        if clock != TimerClock::Monotonic && time::current_clock().is_none() {
+            if let Ok(timer) = sys::ClockTimer::new(clock) {
+                return Self {
+                    kind: TimerKind::Clock(timer),
+                    delay: None,
+                };
+            }
+        }
+
+        let _ = clock;
+        Self::never()
+    }
+
+    /// Create a new timer that fires when the wall clock reaches `time`.
+    ///
+    /// See [`TimerClock::Wall`] for more information.
+    #[inline]
+    pub fn at_system_time(time: SystemTime) -> Self {
+        let mut timer = Self::never_on(TimerClock::Wall);
+        timer.set_at_system_time(time);
+        timer
     }
 
     /// Create a new timer that fires after a specific interval.
     #[inline]
     pub fn after(duration: Duration) -> Self {
-        Instant::now()
-            .checked_add(duration)
-            .map_or_else(Timer::never, Timer::at)
+        let mut timer = Self::never();
+        timer.set_after(duration);
+        timer
     }
 
     /// Create a new timer that fires at a specific deadline.
     #[inline]
     pub fn at(deadline: Instant) -> Self {
-        Self(sys::Timer::at(deadline))
+        let mut timer = Self::never();
+        timer.set_at(deadline);
+        timer
     }
 
     /// Create a new timer that fires on an interval, starting now.
     #[inline]
     pub fn interval(period: Duration) -> Self {
-        Instant::now()
-            .checked_add(period)
-            .map_or_else(Timer::never, |start| Timer::interval_at(start, period))
+        let mut timer = Self::never();
+        timer.set_interval(period);
+        timer
     }
 
     /// Create a new timer that fires on an interval starting at a deadline.
     #[inline]
     pub fn interval_at(start: Instant, period: Duration) -> Self {
-        Self(sys::Timer::interval(start, period))
+        let mut timer = Self::never();
+        timer.set_interval_at(start, period);
+        timer
     }
 
     /// Set this timer to never fire.
     #[inline]
     pub fn set_never(&mut self) {
-        self.0.set_never();
+        self.delay = None;
+        match &mut self.kind {
+            TimerKind::Sys(timer) => timer.set_never(),
+            TimerKind::Virtual(timer) => timer.set_never(),
+            #[cfg(target_os = "linux")]
+            TimerKind::Clock(timer) => timer.set_never(),
+        }
+    }
+
+    /// Set this timer to fire when the wall clock reaches `time`, clearing any prior timer.
+    ///
+    /// On a timer that is not running against [`TimerClock::Wall`], `time` is converted
+    /// to the timer's clock when it is set.
+    #[inline]
+    pub fn set_at_system_time(&mut self, time: SystemTime) {
+        let after = time.duration_since(SystemTime::now()).unwrap_or_default();
+
+        #[cfg(target_os = "linux")]
+        if let TimerKind::Clock(timer) = &mut self.kind {
+            if timer.set_system_time(time, Duration::MAX).is_ok() {
+                self.delay = Some(after);
+                return;
+            }
+        }
+
+        self.set_after(after);
     }
 
     /// Set this timer to fire after a specific duration, clearing any prior timer.
     #[inline]
     pub fn set_after(&mut self, after: Duration) {
-        match Instant::now().checked_add(after) {
+        match time::now().checked_add(after) {
             None => self.set_never(),
-            Some(deadline) => self.set_at(deadline),
+            Some(deadline) => {
+                self.arm(apply_slack(deadline), Duration::MAX);
+                self.delay = Some(after);
+            }
         }
     }
 
     /// Set this timer to fire at a specific deadline, clearing any prior timer.
     #[inline]
     pub fn set_at(&mut self, deadline: Instant) {
-        self.0.set_at(deadline);
+        self.arm(apply_slack(deadline), Duration::MAX);
+        self.delay = Some(deadline.saturating_duration_since(time::now()));
     }
 
     /// Set this timer to fire on an interval, clearing any prior timer.
     #[inline]
     pub fn set_interval(&mut self, period: Duration) {
-        match Instant::now().checked_add(period) {
+        match time::now().checked_add(period) {
             None => self.set_never(),
             Some(start) => self.set_interval_at(start, period),
         }
@@ -198,7 +699,118 @@ impl Timer {
     /// prior timer.
     #[inline]
     pub fn set_interval_at(&mut self, start: Instant, period: Duration) {
-        self.0.set_interval(start, period);
+        self.arm(apply_slack(start), period);
+        self.delay = Some(period);
+    }
+
+    /// Change the period of this timer without moving its phase.
+    ///
+    /// The next tick is scheduled one new period after the last tick, rather than one
+    /// period from now as with [`Timer::set_interval`]. A timer that fires once becomes an
+    /// interval whose first tick is its current deadline. Does nothing if the timer is not
+    /// armed.
+    #[inline]
+    pub fn set_period(&mut self, period: Duration) {
+        let next = match (self.deadline(), self.period()) {
+            (Some(next), Some(old)) => next
+                .checked_sub(old)
+                .and_then(|last| last.checked_add(period))
+                .unwrap_or(next),
+            (Some(next), None) => next,
+            (None, _) => return,
+        };
+
+        self.set_interval_at(next, period);
+    }
+
+    /// Restart this timer from now, keeping its delay.
+    ///
+    /// An interval is restarted so its next tick is one period from now. A timer that
+    /// fires once is re-armed with the delay it was last set with, even if it has already
+    /// fired. Does nothing for a timer that was set to never fire.
+    #[inline]
+    pub fn reset(&mut self) {
+        match (self.period(), self.delay) {
+            (Some(period), _) => self.set_interval(period),
+            (None, Some(delay)) => self.set_after(delay),
+            (None, None) => {}
+        }
+    }
+
+    /// Get the next time this timer fires, or `None` if it will never fire.
+    ///
+    /// This includes any rounding applied by the reactor's timer slack. For timers that
+    /// are not on the monotonic clock, this is an estimate based on the current time.
+    #[inline]
+    pub fn deadline(&self) -> Option<Instant> {
+        match &self.kind {
+            TimerKind::Sys(timer) => timer.deadline(),
+            TimerKind::Virtual(timer) => timer.deadline(),
+            #[cfg(target_os = "linux")]
+            TimerKind::Clock(timer) => {
+                let now = time::now();
+                timer
+                    .remaining()
+                    .map(|left| now.checked_add(left).unwrap_or(now))
+            }
+        }
+    }
+
+    /// Get the time between ticks, or `None` if this timer does not fire repeatedly.
+    #[inline]
+    pub fn period(&self) -> Option<Duration> {
+        match &self.kind {
+            TimerKind::Sys(timer) => timer.period(),
+            TimerKind::Virtual(timer) => timer.period(),
+            #[cfg(target_os = "linux")]
+            TimerKind::Clock(timer) => timer.period(),
+        }
+    }
+
+    /// Get the clock this timer runs against.
+    #[inline]
+    pub fn clock(&self) -> TimerClock {
+        match &self.kind {
+            #[cfg(target_os = "linux")]
+            TimerKind::Clock(timer) => timer.clock(),
+            _ => TimerClock::Monotonic,
+        }
+    }
+
+    /// Tell whether this timer will fire again.
+    #[inline]
+    pub fn is_armed(&self) -> bool {
+        self.deadline().is_some()
+    }
+
+    /// Point the underlying timer at a new deadline.
+    #[inline]
+    fn arm(&mut self, at: Instant, period: Duration) {
+        match &mut self.kind {
+            TimerKind::Sys(timer) if period == Duration::MAX => timer.set_at(at),
+            TimerKind::Sys(timer) => timer.set_interval(at, period),
+            TimerKind::Virtual(timer) => timer.set_interval(at, period),
+            #[cfg(target_os = "linux")]
+            TimerKind::Clock(timer) => {
+                let after = at.saturating_duration_since(time::now());
+                if timer.set_after(after, period).is_err() {
+                    // Fall back to the monotonic clock.
+                    self.kind = Self::never().kind;
+                    self.arm(at, period);
+                }
+            }
+        }
+    }
+
+    /// Wait for the next time this timer fires.
+    #[inline]
+    fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match &mut self.kind {
+            TimerKind::Sys(timer) => timer.poll(cx),
+            TimerKind::Virtual(timer) => timer.poll(cx),
+            #[cfg(target_os = "linux")]
+            TimerKind::Clock(timer) => timer.poll(cx),
+        }
     }
 }
 
@@ -207,7 +819,7 @@ impl Future for Timer {
 
     #[inline]
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.0.poll(cx)
+        self.poll_tick(cx)
     }
 }
 
@@ -216,7 +828,7 @@ impl Stream for Timer {
 
     #[inline]
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.0.poll(cx).map(Some)
+        self.poll_tick(cx).map(Some)
     }
 
     #[inline]
@@ -226,13 +838,60 @@ impl Stream for Timer {
     }
 }
 
+thread_local! {
+    /// The timer slack of the innermost reactor running on this thread.
+    static TIMER_SLACK: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+/// Sets the timer slack for this thread until dropped.
+struct SlackScope(Duration);
+
+impl SlackScope {
+    /// Use the given timer slack until the scope is dropped.
+    #[inline]
+    fn enter(slack: Duration) -> Self {
+        Self(TIMER_SLACK.with(|current| current.replace(slack)))
+    }
+}
+
+impl Drop for SlackScope {
+    #[inline]
+    fn drop(&mut self) {
+        TIMER_SLACK.with(|current| current.set(self.0));
+    }
+}
+
+/// Round a deadline up to the next multiple of the current timer slack.
+///
+/// Timers with deadlines in the same window end up with the same deadline, so they are
+/// woken up together.
+#[inline]
+fn apply_slack(deadline: Instant) -> Instant {
+    let slack = TIMER_SLACK.with(Cell::get).as_nanos();
+    if slack == 0 {
+        return deadline;
+    }
+
+    // Windows are measured from a fixed point so that they line up between timers.
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    let epoch = *EPOCH.get_or_init(Instant::now);
+
+    let delay = match deadline.checked_duration_since(epoch) {
+        Some(after) => (slack - after.as_nanos() % slack) % slack,
+        None => (epoch - deadline).as_nanos() % slack,
+    };
+    u64::try_from(delay)
+        .ok()
+        .and_then(|delay| deadline.checked_add(Duration::from_nanos(delay)))
+        .unwrap_or(deadline)
+}
+
 /// Check if a thread is the main thread.
 #[allow(dead_code)]
 #[inline]
 fn check_main_thread() -> io::Result<()> {
     if !sys::is_main_thread() {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
+        Err(io::Error::other(
             "keter-reactor must be run on the same thread that called main()",
         ))
     } else {
@@ -240,10 +899,21 @@ fn check_main_thread() -> io::Result<()> {
     }
 }
 
+/// Build a reactor for tests, which can run on any thread.
+///
+/// `configure` sets up the rest of the builder.
+#[cfg(all(test, unix, not(target_vendor = "apple"), not(target_os = "android")))]
+pub(crate) fn test_reactor(configure: impl FnOnce(ReactorBuilder) -> ReactorBuilder) -> Reactor {
+    use crate::platform::any_thread::ReactorBuilderExt as _;
+    use crate::platform::instantiation::ReactorBuilderExt as _;
+
+    configure(ReactorBuilder::new()).any_thread(true).build()
+}
+
 #[cfg(not(target_os = "android"))]
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use super::{__report, exit, exit_with, io, Duration, Reactor, Result, Timer};
 
     #[test]
     fn not_allowed_on_any_thread() {
@@ -255,4 +925,229 @@ mod tests {
         .join()
         .unwrap();
     }
+
+    #[crate::test]
+    async fn test_attribute() {
+        Timer::after(Duration::from_millis(1)).await;
+    }
+
+    #[crate::test]
+    #[should_panic(expected = "test failed: the test returned an error")]
+    async fn test_attribute_result() -> Result<()> {
+        Timer::after(Duration::from_millis(1)).await;
+        Err(std::io::Error::other("the test returned an error"))
+    }
+
+    #[test]
+    fn exit_code() {
+        use crate::platform::any_thread::ReactorExt as _;
+        use crate::platform::instantiation::ReactorExt as _;
+
+        let finished = Reactor::new()
+            .with_any_thread()
+            .block_on(async { exit_with(3).await })
+            .unwrap();
+        assert_eq!(finished.exit_code(), 3);
+        assert!(!finished.is_success());
+        assert_eq!(__report(Ok(finished)), 3);
+
+        let finished = Reactor::new()
+            .with_any_thread()
+            .block_on(async { exit().await })
+            .unwrap();
+        assert!(finished.is_success());
+        assert_eq!(__report(Ok(finished)), 0);
+
+        let err = io::Error::new(io::ErrorKind::NotFound, "missing");
+        assert_eq!(__report(Err(err)), 1);
+    }
+
+    #[test]
+    fn reactors_on_threads() {
+        use crate::platform::any_thread::ReactorExt as _;
+        use crate::platform::instantiation::ReactorExt as _;
+
+        // This reactor runs until it is told to exit through its handle.
+        let (send, recv) = std::sync::mpsc::channel();
+        let waiting = std::thread::spawn(move || {
+            let reactor = Reactor::new().with_any_thread();
+            let handle = reactor.exit_handle();
+            reactor.block_on(async move {
+                send.send(handle).unwrap();
+                std::future::pending().await
+            })
+        });
+        let handle = recv.recv().unwrap();
+
+        // These reactors exit on their own without affecting each other.
+        let threads = (1..=4)
+            .map(|code| {
+                std::thread::spawn(move || {
+                    Reactor::new().with_any_thread().block_on(async move {
+                        Timer::after(Duration::from_millis(10 * code as u64)).await;
+                        exit_with(code).await
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        for (code, thread) in (1..=4).zip(threads) {
+            assert_eq!(thread.join().unwrap().unwrap().exit_code(), code);
+        }
+        assert!(!waiting.is_finished());
+        handle.exit_with(7);
+        assert_eq!(waiting.join().unwrap().unwrap().exit_code(), 7);
+    }
+
+    #[test]
+    fn reuse_reactor() {
+        use crate::platform::any_thread::ReactorExt as _;
+        use crate::platform::instantiation::ReactorExt as _;
+
+        let reactor = Reactor::new().with_any_thread();
+        let finished = reactor.block_on(async { exit_with(3).await }).unwrap();
+        assert_eq!(finished.exit_code(), 3);
+
+        // Exiting while the reactor is not running does not affect the next run.
+        reactor.exit_handle().exit_with(5);
+        let finished = reactor
+            .__block_on_result(async {
+                Timer::after(Duration::from_millis(10)).await;
+                Ok(())
+            })
+            .unwrap();
+        assert!(finished.is_success());
+
+        let finished = reactor.block_on(async { exit().await }).unwrap();
+        assert!(finished.is_success());
+    }
+
+    #[test]
+    fn nested_reactors() {
+        use crate::platform::any_thread::ReactorExt as _;
+        use crate::platform::instantiation::ReactorExt as _;
+
+        let outer = Reactor::new().with_any_thread();
+        let finished = outer
+            .block_on(async {
+                // The innermost reactor is the one that exits.
+                let inner = Reactor::new().with_any_thread();
+                let finished = inner.block_on(async { exit_with(4).await }).unwrap();
+                assert_eq!(finished.exit_code(), 4);
+
+                // A reactor cannot be run while it is running.
+                assert!(outer.block_on(async { exit().await }).is_err());
+
+                exit_with(6).await
+            })
+            .unwrap();
+        assert_eq!(finished.exit_code(), 6);
+    }
+
+    #[test]
+    fn timer_slack() {
+        use std::time::Instant;
+
+        let slack = Duration::from_millis(20);
+        let reactor = super::test_reactor(|builder| builder.timer_slack(slack));
+
+        reactor
+            .__block_on_result(async move {
+                // Nearby deadlines are rounded up to the same point in time.
+                let now = Instant::now();
+                let first = super::apply_slack(now);
+                let second = super::apply_slack(now + Duration::from_millis(1));
+                assert!(first >= now && first - now < slack);
+                assert!(second == first || second - first == slack);
+
+                let start = Instant::now();
+                Timer::after(Duration::from_millis(5)).await;
+                assert!(start.elapsed() >= Duration::from_millis(5));
+                Ok(())
+            })
+            .unwrap();
+
+        // Timers created outside of the reactor have no slack.
+        let now = Instant::now();
+        assert_eq!(super::apply_slack(now), now);
+    }
+
+    #[test]
+    fn timer_introspection() {
+        use crate::time::{self, VirtualClock};
+        use futures_lite::{future, StreamExt};
+
+        // Checks that hold for timers on either clock.
+        async fn check() {
+            let ms = Duration::from_millis;
+
+            let timer = Timer::never();
+            assert!(!timer.is_armed());
+            assert_eq!((timer.deadline(), timer.period()), (None, None));
+
+            // Check a deadline set `delay` from now, between `before` and the current time.
+            let armed = |timer: &Timer, before, delay| {
+                let deadline = timer.deadline().unwrap();
+                assert!(deadline >= before + delay && deadline <= time::now() + delay);
+            };
+
+            // One-shot timers disarm once they fire, and can be re-armed.
+            let start = time::now();
+            let mut timer = Timer::after(ms(5));
+            armed(&timer, start, ms(5));
+            assert_eq!(timer.period(), None);
+            (&mut timer).await;
+            assert!(!timer.is_armed());
+            let before = time::now();
+            timer.reset();
+            armed(&timer, before, ms(5));
+            timer.set_never();
+            timer.reset();
+            assert!(!timer.is_armed());
+
+            // Intervals move their deadline forward by one period per tick.
+            let start = time::now();
+            let mut timer = Timer::interval_at(start + ms(2), ms(4));
+            assert_eq!(timer.period(), Some(ms(4)));
+            timer.next().await;
+            assert_eq!(timer.deadline(), Some(start + ms(6)));
+
+            // Changing the period keeps the phase of the last tick.
+            timer.set_period(ms(10));
+            assert_eq!(timer.deadline(), Some(start + ms(12)));
+            assert_eq!(timer.period(), Some(ms(10)));
+
+            // Resetting restarts the interval from now.
+            let before = time::now();
+            timer.reset();
+            armed(&timer, before, ms(10));
+
+            // A one-shot timer becomes an interval starting at its deadline.
+            let now = time::now();
+            let mut timer = Timer::at(now + ms(3));
+            timer.set_period(ms(7));
+            assert_eq!(timer.deadline(), Some(now + ms(3)));
+            assert_eq!(timer.period(), Some(ms(7)));
+            future::poll_once(&mut timer).await;
+        }
+
+        // The platform's timers.
+        let reactor = super::test_reactor(|builder| builder);
+        reactor
+            .__block_on_result(async {
+                check().await;
+                Ok(())
+            })
+            .unwrap();
+
+        // Virtual timers.
+        let reactor = super::test_reactor(|builder| {
+            builder.virtual_clock(VirtualClock::new().with_auto_advance(true))
+        });
+        reactor
+            .__block_on_result(async {
+                check().await;
+                Ok(())
+            })
+            .unwrap();
+    }
 }
@@ -4,8 +4,8 @@
 //!
 //! This is a semver-exempt module used in the `main!` function.
 
-use crate::sys::Settings;
-use crate::Reactor;
+use crate::sys::{Options, Settings};
+use crate::{Reactor, ReactorBuilder};
 
 /// Re-export of the `android_activity` crate.
 pub use android_activity;
@@ -17,8 +17,22 @@ impl Reactor {
     #[doc(hidden)]
     #[inline]
     pub fn __new(app: android_activity::AndroidApp) -> Self {
-        Reactor {
-            settings: Settings::new(app),
-        }
+        ReactorBuilder::new().__build(app)
+    }
+}
+
+impl ReactorBuilder {
+    /// Create a new `Reactor` with these settings from an `AndroidApp`.
+    ///
+    /// This function is not meant to be used in the public API.
+    #[doc(hidden)]
+    #[inline]
+    pub fn __build(self, app: android_activity::AndroidApp) -> Reactor {
+        // There are no Android-specific options yet.
+        let Self {
+            config,
+            settings: Options {},
+        } = self;
+        Reactor::with_settings(config, Settings::new(app))
     }
 }
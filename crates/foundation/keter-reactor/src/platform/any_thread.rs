@@ -2,7 +2,7 @@
 
 //! Extension traits for reactors that can run on any thread.
 
-use crate::Reactor;
+use crate::{Reactor, ReactorBuilder};
 
 /// Extension trait that allows for the [`Reactor`] to be run on any thread.
 ///
@@ -19,3 +19,22 @@ impl ReactorExt for Reactor {
         self
     }
 }
+
+/// Extension trait that allows for a [`ReactorBuilder`] to build reactors that run on any
+/// thread.
+///
+/// [`ReactorBuilder`]: crate::ReactorBuilder
+pub trait ReactorBuilderExt: Sized + crate::platform::sealed::Sealed {
+    /// Set whether the reactor can be run on threads other than the main thread.
+    ///
+    /// By default, the reactor can only be run on the main thread.
+    fn any_thread(self, any_thread: bool) -> Self;
+}
+
+impl ReactorBuilderExt for ReactorBuilder {
+    #[inline]
+    fn any_thread(mut self, any_thread: bool) -> Self {
+        self.settings.any_thread = any_thread;
+        self
+    }
+}
@@ -0,0 +1,343 @@
+// MIT/Apache2 License
+
+//! Drive the reactor from another event loop.
+//!
+//! [`Reactor::block_on`] owns the thread it runs on. Applications that already have an
+//! event loop, such as GTK's main context or a game engine, can instead embed the reactor
+//! into that loop with [`ReactorExt::embed`]. The same futures, [`Timer`]s and
+//! [`poll_io::Async`] sources work as they do in [`Reactor::block_on`].
+//!
+//! The other event loop is responsible for:
+//!
+//! - Waiting for the [`Embedded`] file descriptor to become readable, for no longer than
+//!   [`Embedded::timeout`].
+//! - Calling [`Embedded::dispatch`] whenever it wakes up, until it returns
+//!   [`Poll::Ready`].
+//!
+//! While the reactor is not being dispatched, I/O events and timers are collected on a
+//! background thread, which makes the file descriptor readable when there is work to do.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use keter_reactor::platform::embed::ReactorExt as _;
+//! use keter_reactor::platform::instantiation::ReactorExt as _;
+//! use keter_reactor::Reactor;
+//! use std::os::unix::io::AsFd;
+//! use std::task::Poll;
+//!
+//! # fn wait(_: std::os::unix::io::BorrowedFd<'_>, _: Option<std::time::Duration>) {}
+//! let reactor = Reactor::new();
+//! let mut embedded = reactor.embed(async { keter_reactor::exit().await }).unwrap();
+//!
+//! let finished = loop {
+//!     // Block in the other event loop, however it does that.
+//!     wait(embedded.as_fd(), embedded.timeout());
+//!
+//!     if let Poll::Ready(finished) = embedded.dispatch() {
+//!         break finished.unwrap();
+//!     }
+//! };
+//! ```
+//!
+//! [`Reactor::block_on`]: crate::Reactor::block_on
+//! [`Timer`]: crate::Timer
+//! [`poll_io::Async`]: crate::platform::poll_io::Async
+
+use crate::{Finished, Reactor, Run};
+
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsFd, BorrowedFd};
+use std::os::unix::net::UnixStream;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use web_time::{Duration, Instant};
+
+/// The future that runs an embedded reactor.
+type ReactorFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Result<Infallible, i32>>> + 'a>>;
+
+/// Extension trait that allows the [`Reactor`] to be driven by another event loop.
+///
+/// [`Reactor`]: crate::Reactor
+pub trait ReactorExt: crate::platform::sealed::Sealed {
+    /// Start running a future on this reactor, without blocking the thread.
+    ///
+    /// The reactor runs every time the returned [`Embedded`] is dispatched, and counts as
+    /// running until it finishes or is dropped. Fails if the reactor is already running.
+    fn embed<'a>(
+        &'a self,
+        future: impl Future<Output = Infallible> + 'a,
+    ) -> io::Result<Embedded<'a>>;
+}
+
+impl ReactorExt for Reactor {
+    fn embed<'a>(
+        &'a self,
+        future: impl Future<Output = Infallible> + 'a,
+    ) -> io::Result<Embedded<'a>> {
+        let run = Run::start(self)?;
+        let future = {
+            let _scope = run.enter();
+            let future = run.wrap(Box::pin(future));
+            crate::sys::run(&self.settings, &self.config, &self.exit, future)?
+        };
+
+        // The reactor has to be polled once to get started.
+        let (receiver, sender) = UnixStream::pair()?;
+        receiver.set_nonblocking(true)?;
+        sender.set_nonblocking(true)?;
+        let notifier = Arc::new(Notifier {
+            notified: AtomicBool::new(false),
+            sender,
+        });
+        notifier.wake_by_ref();
+
+        Ok(Embedded {
+            future: Some(Box::pin(future)),
+            finished: None,
+            notifier,
+            receiver,
+            run,
+        })
+    }
+}
+
+/// A reactor being driven by another event loop.
+///
+/// This is returned by [`ReactorExt::embed`]. Dropping it before it finishes stops the
+/// reactor without running the hooks registered with [`on_exit`].
+///
+/// [`on_exit`]: crate::on_exit
+pub struct Embedded<'a> {
+    /// The future running the reactor, or `None` once it finishes.
+    future: Option<ReactorFuture<'a>>,
+
+    /// The exit code the reactor finished with, or the error it failed with.
+    finished: Option<Result<i32, Arc<io::Error>>>,
+
+    /// Wakes up the other event loop.
+    notifier: Arc<Notifier>,
+
+    /// Readable whenever the reactor needs to be dispatched.
+    receiver: UnixStream,
+
+    /// The state of the reactor run.
+    run: Run<'a>,
+}
+
+impl fmt::Debug for Embedded<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Embedded")
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsFd for Embedded<'_> {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.receiver.as_fd()
+    }
+}
+
+impl Embedded<'_> {
+    /// Run the work that woke up the reactor.
+    ///
+    /// This clears the readiness of the file descriptor before running. Returns
+    /// [`Poll::Ready`] once the reactor finishes, and every time after that. Returns an
+    /// error if the reactor failed, and an error with the same kind and message every
+    /// time after that.
+    pub fn dispatch(&mut self) -> Poll<io::Result<Finished>> {
+        // Clear out old notifications before polling, so wakeups during the poll are kept.
+        let mut buffer = [0u8; 64];
+        while matches!((&self.receiver).read(&mut buffer), Ok(n) if n > 0) {}
+        self.notifier.notified.store(false, Ordering::SeqCst);
+
+        self.poll_once()
+    }
+
+    /// Poll the reactor once, without clearing the readiness of the file descriptor.
+    ///
+    /// This is useful for event loops that poll the reactor on their own schedule, like
+    /// once per frame. Returns [`Poll::Ready`] once the reactor finishes, and every time
+    /// after that.
+    pub fn poll_once(&mut self) -> Poll<io::Result<Finished>> {
+        match &self.finished {
+            Some(Ok(exit_code)) => return Poll::Ready(Ok(Finished::new(*exit_code))),
+            Some(Err(err)) => return Poll::Ready(Err(io::Error::new(err.kind(), err.clone()))),
+            None => {}
+        }
+        let Some(future) = &mut self.future else {
+            return Poll::Pending;
+        };
+
+        let _scope = self.run.enter();
+        let waker = Waker::from(self.notifier.clone());
+        match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(Ok(Ok(infallible))) => match infallible {},
+            Poll::Ready(Ok(Err(exit_code))) => {
+                self.future = None;
+                self.run.set_exit_code(exit_code);
+                self.finished = Some(Ok(exit_code));
+                Poll::Ready(Ok(Finished::new(exit_code)))
+            }
+            Poll::Ready(Err(err)) => {
+                // Keep the error around so later calls can report it too.
+                let err = Arc::new(err);
+                self.future = None;
+                self.finished = Some(Err(err.clone()));
+                Poll::Ready(Err(io::Error::new(err.kind(), err)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    /// Get the deadline of the reactor's next timer, if it is known.
+    ///
+    /// The background thread wakes up the reactor for its timers, so this is only a hint
+    /// for event loops that want to avoid the extra wakeup.
+    #[inline]
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let _scope = self.run.enter();
+        crate::time::next_deadline()
+    }
+
+    /// Get how long the other event loop can wait before dispatching the reactor again.
+    ///
+    /// This is zero if the reactor has work to do right now, and `None` if it can wait
+    /// until the file descriptor becomes readable.
+    #[inline]
+    pub fn timeout(&self) -> Option<Duration> {
+        if self.finished.is_some() || self.notifier.notified.load(Ordering::SeqCst) {
+            return Some(Duration::ZERO);
+        }
+
+        let _scope = self.run.enter();
+        crate::time::next_deadline()
+            .map(|deadline| deadline.saturating_duration_since(crate::time::now()))
+    }
+}
+
+impl Drop for Embedded<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        // Drop the reactor's future while its state is still around.
+        let _scope = self.run.enter();
+        self.future = None;
+    }
+}
+
+/// Wakes up the other event loop by making a file descriptor readable.
+struct Notifier {
+    /// Whether the reactor has been woken since it was last dispatched.
+    notified: AtomicBool,
+
+    /// The end of the socket pair that is written to.
+    sender: UnixStream,
+}
+
+impl Wake for Notifier {
+    #[inline]
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    #[inline]
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.notified.swap(true, Ordering::SeqCst) {
+            // If the socket is full, it is already readable.
+            let _ = (&self.sender).write(&[1]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::poll_io::Async;
+    use crate::{exit, exit_with, on_exit, spawn_local, test_reactor, Timer};
+
+    use futures_lite::{AsyncReadExt, StreamExt};
+    use rustix::event::{poll, PollFd, PollFlags, Timespec};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn embedded() {
+        let reactor = test_reactor(|builder| builder);
+        let (proxy, mut messages) = reactor.proxy::<u32>();
+        let (reader, mut writer) = UnixStream::pair().unwrap();
+        let cleaned_up = Rc::new(Cell::new(false));
+
+        let mut embedded = reactor
+            .embed({
+                let cleaned_up = cleaned_up.clone();
+                async move {
+                    // Timers fire.
+                    let start = Instant::now();
+                    Timer::after(Duration::from_millis(10)).await;
+                    assert!(start.elapsed() >= Duration::from_millis(10));
+
+                    // Spawned tasks run.
+                    assert_eq!(spawn_local(async { 1 }).await, 1);
+
+                    // I/O sources become ready.
+                    let mut reader = Async::new(reader).unwrap();
+                    let mut byte = [0u8];
+                    reader.read_exact(&mut byte).await.unwrap();
+                    assert_eq!(byte, [7]);
+
+                    // Messages from other threads arrive.
+                    assert_eq!(messages.next().await, Some(5));
+
+                    on_exit(async move { cleaned_up.set(true) });
+                    exit_with(3).await
+                }
+            })
+            .unwrap();
+
+        // Running the reactor elsewhere fails while it is embedded.
+        assert!(reactor.block_on(async { exit().await }).is_err());
+
+        let other_thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            writer.write_all(&[7]).unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+            proxy.send(5).unwrap();
+        });
+
+        // Drive the reactor from a plain poll(2) loop.
+        let mut iterations = 0;
+        let finished = loop {
+            let timeout = embedded
+                .timeout()
+                .map(|timeout| Timespec::try_from(timeout).unwrap());
+            let mut fds = [PollFd::new(&embedded, PollFlags::IN)];
+            poll(&mut fds, timeout.as_ref()).unwrap();
+
+            if let Poll::Ready(finished) = embedded.dispatch() {
+                break finished.unwrap();
+            }
+
+            iterations += 1;
+            assert!(iterations < 1_000, "the reactor is spinning");
+        };
+
+        assert_eq!(finished.exit_code(), 3);
+        assert!(embedded.dispatch().is_ready());
+        assert!(cleaned_up.get());
+        other_thread.join().unwrap();
+
+        // The reactor can run normally once the embedding is gone.
+        drop(embedded);
+        let finished = reactor.block_on(async { exit_with(4).await }).unwrap();
+        assert_eq!(finished.exit_code(), 4);
+    }
+}
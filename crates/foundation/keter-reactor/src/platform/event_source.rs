@@ -0,0 +1,347 @@
+// MIT/Apache2 License
+
+//! Connections to display servers and other sources of events.
+//!
+//! Display server connections often cannot be treated as plain file descriptors. Wayland
+//! requires a call to `prepare_read` before sleeping and either `read_events` or
+//! `cancel_read` afterwards, and both Wayland and X11 can hold events in a queue in memory,
+//! which leaves nothing to read from the file descriptor. An [`EventSource`] describes these
+//! phases, and the reactor calls them in the right order around sleeping:
+//!
+//! 1. Right before the reactor goes to sleep, after all other work has run, it calls
+//!    [`EventSource::flush`] and then [`EventSource::prepare`].
+//! 2. If `prepare` reports that events are already queued, the reactor calls
+//!    [`EventSource::dispatch`] right away instead of sleeping.
+//! 3. Otherwise, the reactor sleeps until the file descriptor becomes readable or something
+//!    else wakes it up. Once it wakes up, it calls [`EventSource::dispatch`] before running
+//!    anything else.
+
+use crate::idle::{self, SleepSource, SourceGuard};
+use crate::platform::poll_io::Async;
+
+use std::cell::{Ref, RefCell, RefMut};
+use std::fmt;
+use std::io;
+use std::os::unix::io::{AsFd, OwnedFd};
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+/// A source of events with separate phases for preparing, reading and dispatching.
+///
+/// See the [module-level documentation](self) for the order these are called in.
+pub trait EventSource: AsFd + 'static {
+    /// Get ready for the reactor to go to sleep.
+    ///
+    /// Returns `Ok(true)` if events are already queued, so that the reactor dispatches them
+    /// instead of sleeping. Otherwise, the reactor waits for the file descriptor to become
+    /// readable.
+    fn prepare(&mut self) -> io::Result<bool>;
+
+    /// Read and handle events.
+    ///
+    /// This is called exactly once after every call to [`EventSource::prepare`].
+    /// `readable` tells whether the file descriptor is readable; if it is not, the
+    /// reactor woke up for some other reason, and any read prepared in `prepare` should be
+    /// cancelled.
+    fn dispatch(&mut self, readable: bool) -> io::Result<()>;
+
+    /// Write out any buffered requests.
+    ///
+    /// This is called right before [`EventSource::prepare`]. Returning an error of kind
+    /// [`WouldBlock`] makes the reactor wait for the file descriptor to become writable
+    /// and try again.
+    ///
+    /// [`WouldBlock`]: io::ErrorKind::WouldBlock
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+/// Register an event source with the reactor running on this thread.
+///
+/// The source is unregistered once the returned [`Registration`] is dropped, or once one
+/// of its hooks returns an error.
+///
+/// # Panics
+///
+/// Panics if no reactor is running on this thread.
+pub fn register<S: EventSource>(source: S) -> io::Result<Registration<S>> {
+    // Keep the file descriptor in whatever mode the source expects.
+    let fd = Async::with_nonblocking(source.as_fd().try_clone_to_owned()?)?;
+
+    let inner = Rc::new(RefCell::new(Inner {
+        source,
+        fd,
+        prepared: false,
+        error: None,
+        waker: None,
+    }));
+    let guard = idle::add_source(inner.clone());
+
+    Ok(Registration {
+        inner,
+        guard: RefCell::new(Some(guard)),
+    })
+}
+
+/// An [`EventSource`] registered with the reactor.
+///
+/// This is returned by [`register`].
+pub struct Registration<S> {
+    /// The shared state of the source.
+    inner: Rc<RefCell<Inner<S>>>,
+
+    /// Keeps the source registered.
+    guard: RefCell<Option<SourceGuard>>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for Registration<S> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inner.try_borrow() {
+            Ok(inner) => f
+                .debug_struct("Registration")
+                .field("source", &inner.source)
+                .finish_non_exhaustive(),
+            Err(_) => f.debug_struct("Registration").finish_non_exhaustive(),
+        }
+    }
+}
+
+impl<S> Registration<S> {
+    /// Get a reference to the source.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside one of the source's own hooks.
+    #[inline]
+    pub fn get_ref(&self) -> Ref<'_, S> {
+        Ref::map(self.inner.borrow(), |inner| &inner.source)
+    }
+
+    /// Get a mutable reference to the source.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside one of the source's own hooks.
+    #[inline]
+    pub fn get_mut(&self) -> RefMut<'_, S> {
+        RefMut::map(self.inner.borrow_mut(), |inner| &mut inner.source)
+    }
+
+    /// Poll for an error returned by one of the source's hooks.
+    ///
+    /// Once a hook fails, the source is unregistered.
+    #[inline]
+    pub fn poll_error(&self, cx: &mut Context<'_>) -> Poll<io::Error> {
+        let mut inner = self.inner.borrow_mut();
+        match inner.error.take() {
+            Some(error) => {
+                // Stop checking on the source.
+                self.guard.borrow_mut().take();
+                Poll::Ready(error)
+            }
+            None => {
+                inner.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Wait for one of the source's hooks to return an error.
+    ///
+    /// Once a hook fails, the source is unregistered.
+    #[inline]
+    pub async fn error(&self) -> io::Error {
+        futures_lite::future::poll_fn(|cx| self.poll_error(cx)).await
+    }
+}
+
+/// The state of a registered [`EventSource`].
+struct Inner<S> {
+    /// The source.
+    source: S,
+
+    /// A copy of the source's file descriptor, registered with the reactor.
+    fd: Async<OwnedFd>,
+
+    /// Whether `prepare` was called without a matching `dispatch`.
+    prepared: bool,
+
+    /// The error returned by one of the hooks.
+    error: Option<io::Error>,
+
+    /// Woken when a hook fails.
+    waker: Option<Waker>,
+}
+
+impl<S: EventSource> Inner<S> {
+    /// Run a hook, recording its error.
+    #[inline]
+    fn check(&mut self, result: io::Result<()>) {
+        if let Err(error) = result {
+            self.prepared = false;
+            self.error.get_or_insert(error);
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<S: EventSource> SleepSource for Inner<S> {
+    fn after_wake(&mut self, cx: &mut Context<'_>) {
+        if !self.prepared || self.error.is_some() {
+            return;
+        }
+
+        self.prepared = false;
+        let readable = self.fd.poll_readable(cx).is_ready();
+        let result = self.source.dispatch(readable);
+        self.check(result);
+    }
+
+    fn before_sleep(&mut self, cx: &mut Context<'_>) {
+        if self.prepared || self.error.is_some() {
+            return;
+        }
+
+        // Write out requests, waiting for room if the connection is full.
+        match self.source.flush() {
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                if self.fd.poll_writable(cx).is_ready() {
+                    cx.waker().wake_by_ref();
+                }
+            }
+            result => self.check(result),
+        }
+        if self.error.is_some() {
+            return;
+        }
+
+        match self.source.prepare() {
+            // Handle queued events instead of sleeping.
+            Ok(true) => {
+                let result = self.source.dispatch(false);
+                self.check(result);
+                cx.waker().wake_by_ref();
+            }
+
+            // Sleep until there is something to read.
+            Ok(false) => {
+                self.prepared = true;
+                if self.fd.poll_readable(cx).is_ready() {
+                    cx.waker().wake_by_ref();
+                }
+            }
+
+            Err(error) => self.check(Err(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_reactor;
+
+    use std::collections::VecDeque;
+    use std::io::{Read, Write};
+    use std::os::unix::io::BorrowedFd;
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn event_source() {
+        /// A fake display connection that queues events in memory.
+        struct Connection {
+            stream: UnixStream,
+            queue: VecDeque<u8>,
+            outgoing: Vec<u8>,
+            events: async_channel::Sender<u8>,
+            log: Vec<String>,
+            fail: bool,
+        }
+
+        impl AsFd for Connection {
+            fn as_fd(&self) -> BorrowedFd<'_> {
+                self.stream.as_fd()
+            }
+        }
+
+        impl EventSource for Connection {
+            fn prepare(&mut self) -> io::Result<bool> {
+                self.log.push("prepare".into());
+                if self.fail {
+                    return Err(io::Error::other("connection lost"));
+                }
+                Ok(!self.queue.is_empty())
+            }
+
+            fn dispatch(&mut self, readable: bool) -> io::Result<()> {
+                self.log.push(format!("dispatch {readable}"));
+                if readable {
+                    let mut buffer = [0u8; 16];
+                    let n = self.stream.read(&mut buffer)?;
+                    self.queue.extend(&buffer[..n]);
+                }
+                for event in self.queue.drain(..) {
+                    self.events.try_send(event).unwrap();
+                }
+                Ok(())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                self.log.push("flush".into());
+                self.stream.write_all(&self.outgoing)?;
+                self.outgoing.clear();
+                Ok(())
+            }
+        }
+
+        let reactor = test_reactor(|builder| builder);
+        reactor
+            .__block_on_result(async {
+                let (stream, mut server) = UnixStream::pair()?;
+                let (sender, events) = async_channel::unbounded();
+                let connection = register(Connection {
+                    stream,
+                    queue: VecDeque::new(),
+                    outgoing: Vec::new(),
+                    events: sender,
+                    log: Vec::new(),
+                    fail: false,
+                })?;
+
+                // Queued events are dispatched without the file descriptor being readable.
+                connection.get_mut().queue.push_back(1);
+                assert_eq!(events.recv().await.unwrap(), 1);
+                assert_eq!(
+                    connection.get_ref().log[..3],
+                    ["flush", "prepare", "dispatch false"]
+                );
+
+                // Requests are flushed before sleeping, and replies are read once readable.
+                connection.get_mut().log.clear();
+                connection.get_mut().outgoing.push(9);
+                let server = std::thread::spawn(move || {
+                    let mut request = [0u8];
+                    server.read_exact(&mut request).unwrap();
+                    server.write_all(&[request[0] + 1]).unwrap();
+                    server
+                });
+                assert_eq!(events.recv().await.unwrap(), 10);
+                assert_eq!(connection.get_ref().log[..2], ["flush", "prepare"]);
+                assert!(connection
+                    .get_ref()
+                    .log
+                    .iter()
+                    .any(|entry| entry == "dispatch true"));
+
+                // Errors are reported through the registration.
+                connection.get_mut().fail = true;
+                let error = connection.error().await;
+                assert_eq!(error.to_string(), "connection lost");
+                drop(server.join().unwrap());
+                Ok(())
+            })
+            .unwrap();
+    }
+}
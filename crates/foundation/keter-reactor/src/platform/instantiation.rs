@@ -4,7 +4,7 @@
 //!
 //! [`Reactor`]: crate::Reactor
 
-use crate::Reactor;
+use crate::{Reactor, ReactorBuilder};
 
 /// Allows the user to instantiate the [`Reactor`] freely.
 ///
@@ -19,8 +19,24 @@ pub trait ReactorExt: Sized + crate::platform::sealed::Sealed {
 impl ReactorExt for Reactor {
     #[inline]
     fn new() -> Self {
-        Reactor {
-            settings: crate::sys::Settings::empty(),
-        }
+        ReactorBuilder::new().build()
+    }
+}
+
+/// Allows the user to build a [`Reactor`] from a [`ReactorBuilder`].
+///
+/// [`Reactor`]: crate::Reactor
+/// [`ReactorBuilder`]: crate::ReactorBuilder
+pub trait ReactorBuilderExt: Sized + crate::platform::sealed::Sealed {
+    /// Create a new [`Reactor`] with the settings from this builder.
+    ///
+    /// [`Reactor`]: crate::Reactor
+    fn build(self) -> Reactor;
+}
+
+impl ReactorBuilderExt for ReactorBuilder {
+    #[inline]
+    fn build(self) -> Reactor {
+        Reactor::with_settings(self.config, self.settings)
     }
 }
@@ -0,0 +1,36 @@
+// MIT/Apache2 License
+
+//! Extension traits for running the [`Reactor`] on io_uring.
+//!
+//! By default, the reactor waits for events with epoll. With io_uring, each iteration of
+//! the reactor's loop submits its polls and sleeps in a single system call. [`Timer`]s,
+//! [`poll_io::Async`] sources and the exit signal all work the same way on either.
+//!
+//! [`Reactor`]: crate::Reactor
+//! [`Timer`]: crate::Timer
+//! [`poll_io::Async`]: crate::platform::poll_io::Async
+
+use crate::ReactorBuilder;
+
+/// Extension trait that allows a [`ReactorBuilder`] to build reactors that run on
+/// io_uring.
+///
+/// [`ReactorBuilder`]: crate::ReactorBuilder
+pub trait ReactorBuilderExt: Sized + crate::platform::sealed::Sealed {
+    /// Set whether the reactor runs on io_uring instead of epoll.
+    ///
+    /// If io_uring is not available, because the kernel is older than Linux 5.11 or it has
+    /// been disabled, the reactor falls back to epoll. Reactors embedded through
+    /// [`embed`] always use epoll. By default, the reactor uses epoll.
+    ///
+    /// [`embed`]: crate::platform::embed
+    fn io_uring(self, io_uring: bool) -> Self;
+}
+
+impl ReactorBuilderExt for ReactorBuilder {
+    #[inline]
+    fn io_uring(mut self, io_uring: bool) -> Self {
+        self.settings.io_uring = io_uring;
+        self
+    }
+}
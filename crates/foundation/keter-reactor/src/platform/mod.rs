@@ -8,12 +8,30 @@ pub mod android;
 #[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
 pub mod any_thread;
 #[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+pub mod embed;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+pub mod event_source;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
 pub mod instantiation;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod io_uring;
 #[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
 pub mod poll_io;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+pub mod process;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+pub mod signal;
+#[cfg(all(
+    unix,
+    not(target_vendor = "apple"),
+    not(target_os = "android"),
+    feature = "x11"
+))]
+pub mod x11;
 
 mod sealed {
     #[doc(hidden)]
     pub trait Sealed {}
     impl Sealed for crate::Reactor {}
+    impl Sealed for crate::ReactorBuilder {}
 }
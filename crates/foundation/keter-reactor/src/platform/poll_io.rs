@@ -4,41 +4,90 @@
 //!
 //! This is available on open-source Unixes, and can maybe be added to Apple Unixes.
 
+use futures_io::{AsyncRead, AsyncWrite};
 use futures_lite::prelude::*;
+use futures_lite::stream;
+use rustix::net::addr::SocketAddrArg;
+use rustix::net::AddressFamily;
 
+use std::error::Error;
 use std::fmt;
 use std::future::Future;
-use std::io;
-use std::net::{SocketAddr, TcpListener, TcpStream};
-use std::os::unix::io::AsFd;
-use std::os::unix::net::{UnixListener, UnixStream};
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::mem::MaybeUninit;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::os::unix::io::{AsFd, BorrowedFd, OwnedFd};
+use std::os::unix::net::{SocketAddr as UnixSocketAddr, UnixDatagram, UnixListener, UnixStream};
 use std::path::Path;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use crate::sys::uring::{self, Interest};
+
+#[doc(no_inline)]
+pub use async_io::IoSafe;
+
 /// A wrapper around an I/O source that allows itself to be polled on the reactor.
-pub struct Async<T>(async_io::Async<T>);
+///
+/// If `T` implements [`Read`] or [`Write`], this type implements [`AsyncRead`] or
+/// [`AsyncWrite`], respectively.
+///
+/// Sources created while a reactor runs on io_uring are polled through its ring. All
+/// others are polled with `async-io`.
+pub struct Async<T>(Registration<T>);
+
+/// Where an [`Async`] is registered.
+enum Registration<T> {
+    /// With `async-io`.
+    Io(async_io::Async<T>),
+
+    /// With the io_uring of the reactor that created the source.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    Uring {
+        /// The registration. This is dropped before the I/O source is closed.
+        source: uring::Source,
+
+        /// The I/O source.
+        io: T,
+    },
+}
 
 impl<T: fmt::Debug> fmt::Debug for Async<T> {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Async")
-            .field("inner", self.0.get_ref())
+            .field("inner", self.get_ref())
             .finish_non_exhaustive()
     }
 }
 
+impl<T> Unpin for Async<T> {}
+
 impl<T: AsFd> Async<T> {
     /// Create a new `Async<T>` wrapping around an I/O source.
     #[inline]
     pub fn new(io: T) -> io::Result<Self> {
-        async_io::Async::new(io).map(Self)
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if let Some(driver) = uring::current() {
+            rustix::io::ioctl_fionbio(&io, true)?;
+            let source = uring::Source::new(driver, io.as_fd())?;
+            return Ok(Self(Registration::Uring { source, io }));
+        }
+
+        async_io::Async::new(io).map(|io| Self(Registration::Io(io)))
     }
 
     /// Create a new `Async<T>` without setting the I/O source into non-blocking mode.
     #[inline]
     pub fn with_nonblocking(io: T) -> io::Result<Self> {
-        async_io::Async::new_nonblocking(io).map(Self)
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if let Some(driver) = uring::current() {
+            let source = uring::Source::new(driver, io.as_fd())?;
+            return Ok(Self(Registration::Uring { source, io }));
+        }
+
+        async_io::Async::new_nonblocking(io).map(|io| Self(Registration::Io(io)))
     }
 }
 
@@ -46,37 +95,312 @@ impl<T> Async<T> {
     /// Get a reference to the underlying type.
     #[inline]
     pub fn get_ref(&self) -> &T {
-        self.0.get_ref()
+        match &self.0 {
+            Registration::Io(io) => io.get_ref(),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { io, .. } => io,
+        }
     }
 
     /// Convert this back into a `T`.
     #[inline]
     pub fn into_inner(self) -> io::Result<T> {
-        self.0.into_inner()
+        match self.0 {
+            Registration::Io(io) => io.into_inner(),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                drop(source);
+                Ok(io)
+            }
+        }
     }
 
     /// Polls the I/O handle for readability.
     #[inline]
     pub fn poll_readable(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        self.0.poll_readable(cx)
+        match &self.0 {
+            Registration::Io(io) => io.poll_readable(cx),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, .. } => source.poll_ready(Interest::Readable, cx),
+        }
     }
 
     /// Polls the I/O handle for writability.
     #[inline]
     pub fn poll_writable(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        self.0.poll_writable(cx)
+        match &self.0 {
+            Registration::Io(io) => io.poll_writable(cx),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, .. } => source.poll_ready(Interest::Writable, cx),
+        }
     }
 
     /// Waits for this I/O handle to become readable.
     #[inline]
     pub fn readable(&self) -> Readable<'_, T> {
-        Readable(self.0.readable())
+        match &self.0 {
+            Registration::Io(io) => Readable(ReadableInner::Io(io.readable())),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, .. } => Readable(ReadableInner::Uring(source)),
+        }
     }
 
     /// Waits for this I/O handle to become writable.
     #[inline]
     pub fn writable(&self) -> Writable<'_, T> {
-        Writable(self.0.writable())
+        match &self.0 {
+            Registration::Io(io) => Writable(WritableInner::Io(io.writable())),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, .. } => Writable(WritableInner::Uring(source)),
+        }
+    }
+
+    /// Performs a read operation, waiting for readability if it would block.
+    ///
+    /// The closure is called until it returns anything other than
+    /// [`io::ErrorKind::WouldBlock`].
+    #[inline]
+    pub async fn read_with<R>(&self, op: impl FnMut(&T) -> io::Result<R>) -> io::Result<R> {
+        match &self.0 {
+            Registration::Io(io) => io.read_with(op).await,
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { io, .. } => {
+                let mut op = op;
+                loop {
+                    match op(io) {
+                        Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                            self.readable().await?
+                        }
+                        result => return result,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Performs a write operation, waiting for writability if it would block.
+    ///
+    /// The closure is called until it returns anything other than
+    /// [`io::ErrorKind::WouldBlock`].
+    #[inline]
+    pub async fn write_with<R>(&self, op: impl FnMut(&T) -> io::Result<R>) -> io::Result<R> {
+        match &self.0 {
+            Registration::Io(io) => io.write_with(op).await,
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { io, .. } => {
+                let mut op = op;
+                loop {
+                    match op(io) {
+                        Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                            self.writable().await?
+                        }
+                        result => return result,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Run a non-blocking operation on a source registered with io_uring, waiting for the
+/// source to become ready if it would block.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+#[inline]
+fn poll_uring<R>(
+    source: &uring::Source,
+    interest: Interest,
+    cx: &mut Context<'_>,
+    mut op: impl FnMut() -> io::Result<R>,
+) -> Poll<io::Result<R>> {
+    loop {
+        match op() {
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                futures_lite::ready!(source.poll_ready(interest, cx))?;
+            }
+            result => return Poll::Ready(result),
+        }
+    }
+}
+
+impl<T: IoSafe + Read> AsyncRead for Async<T> {
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match &mut self.get_mut().0 {
+            Registration::Io(io) => Pin::new(io).poll_read(cx, buf),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Readable, cx, || io.read(buf))
+            }
+        }
+    }
+
+    #[inline]
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        match &mut self.get_mut().0 {
+            Registration::Io(io) => Pin::new(io).poll_read_vectored(cx, bufs),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Readable, cx, || io.read_vectored(bufs))
+            }
+        }
+    }
+}
+
+impl<T> AsyncRead for &Async<T>
+where
+    for<'a> &'a T: Read,
+{
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match &self.0 {
+            Registration::Io(io) => Pin::new(&mut &*io).poll_read(cx, buf),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Readable, cx, || (&*io).read(buf))
+            }
+        }
+    }
+
+    #[inline]
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        match &self.0 {
+            Registration::Io(io) => Pin::new(&mut &*io).poll_read_vectored(cx, bufs),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Readable, cx, || {
+                    (&*io).read_vectored(bufs)
+                })
+            }
+        }
+    }
+}
+
+impl<T: IoSafe + Write> AsyncWrite for Async<T> {
+    #[inline]
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match &mut self.get_mut().0 {
+            Registration::Io(io) => Pin::new(io).poll_write(cx, buf),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Writable, cx, || io.write(buf))
+            }
+        }
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        match &mut self.get_mut().0 {
+            Registration::Io(io) => Pin::new(io).poll_write_vectored(cx, bufs),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Writable, cx, || io.write_vectored(bufs))
+            }
+        }
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().0 {
+            Registration::Io(io) => Pin::new(io).poll_flush(cx),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Writable, cx, || io.flush())
+            }
+        }
+    }
+
+    #[inline]
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().0 {
+            Registration::Io(io) => Pin::new(io).poll_close(cx),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Writable, cx, || io.flush())
+            }
+        }
+    }
+}
+
+impl<T> AsyncWrite for &Async<T>
+where
+    for<'a> &'a T: Write,
+{
+    #[inline]
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match &self.0 {
+            Registration::Io(io) => Pin::new(&mut &*io).poll_write(cx, buf),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Writable, cx, || (&*io).write(buf))
+            }
+        }
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        match &self.0 {
+            Registration::Io(io) => Pin::new(&mut &*io).poll_write_vectored(cx, bufs),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Writable, cx, || {
+                    (&*io).write_vectored(bufs)
+                })
+            }
+        }
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &self.0 {
+            Registration::Io(io) => Pin::new(&mut &*io).poll_flush(cx),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Writable, cx, || (&*io).flush())
+            }
+        }
+    }
+
+    #[inline]
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &self.0 {
+            Registration::Io(io) => Pin::new(&mut &*io).poll_close(cx),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Registration::Uring { source, io } => {
+                poll_uring(source, Interest::Writable, cx, || (&*io).flush())
+            }
+        }
     }
 }
 
@@ -84,22 +408,23 @@ impl Async<TcpListener> {
     /// Bind to a specific TCP socket.
     #[inline]
     pub fn bind(address: impl Into<SocketAddr>) -> io::Result<Self> {
-        async_io::Async::<TcpListener>::bind(address.into()).map(Self)
+        Async::new(TcpListener::bind(address.into())?)
     }
 
     /// Wait for a new TCP connection.
     #[inline]
     pub async fn accept(&self) -> io::Result<(Async<TcpStream>, SocketAddr)> {
-        self.0
-            .accept()
-            .await
-            .map(|(socket, addr)| (Async(socket), addr))
+        let (socket, addr) = self.read_with(|listener| listener.accept()).await?;
+        Ok((Async::new(socket)?, addr))
     }
 
     /// Wait for a stream of incoming TCP connections.
     #[inline]
     pub fn incoming(&self) -> impl Stream<Item = io::Result<Async<TcpStream>>> + Send + '_ {
-        self.0.incoming().map(|res| res.map(Async))
+        stream::unfold(self, |listener| async move {
+            let socket = listener.accept().await.map(|(socket, _)| socket);
+            Some((socket, listener))
+        })
     }
 }
 
@@ -107,9 +432,186 @@ impl Async<TcpStream> {
     /// Connect to a specific TCP socket.
     #[inline]
     pub async fn connect(address: impl Into<SocketAddr>) -> io::Result<Self> {
-        async_io::Async::<TcpStream>::connect(address.into())
-            .await
-            .map(Self)
+        let address = address.into();
+        let family = match address {
+            SocketAddr::V4(_) => AddressFamily::INET,
+            SocketAddr::V6(_) => AddressFamily::INET6,
+        };
+        connect(family, &address).await
+    }
+
+    /// Connect to a host by name.
+    ///
+    /// The host name is resolved on a background thread. The resulting addresses are then
+    /// raced against each other using the "Happy Eyeballs" algorithm from [RFC 8305]. If
+    /// every connection attempt fails, the returned error wraps a [`ConnectError`]
+    /// describing each failure.
+    ///
+    /// [RFC 8305]: https://www.rfc-editor.org/rfc/rfc8305
+    pub async fn connect_to_host(host: &str, port: u16) -> io::Result<Self> {
+        // Resolve the host name without blocking the reactor.
+        let host = host.to_owned();
+        let addresses = blocking::unblock(move || {
+            (host.as_str(), port)
+                .to_socket_addrs()
+                .map(Iterator::collect::<Vec<_>>)
+        })
+        .await?;
+
+        happy_eyeballs(interleave_families(addresses)).await
+    }
+}
+
+/// The delay between starting connection attempts, as recommended by RFC 8305.
+const CONNECTION_ATTEMPT_DELAY: web_time::Duration = web_time::Duration::from_millis(250);
+
+/// Order addresses so that address families alternate, starting with the first family.
+fn interleave_families(addresses: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let prefer_v6 = !matches!(addresses.first(), Some(SocketAddr::V4(_)));
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = addresses
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == prefer_v6);
+    preferred.reverse();
+    other.reverse();
+
+    let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+    loop {
+        match (preferred.pop(), other.pop()) {
+            (None, None) => return ordered,
+            (first, second) => ordered.extend(first.into_iter().chain(second)),
+        }
+    }
+}
+
+/// Race connection attempts to a list of addresses.
+async fn happy_eyeballs(addresses: Vec<SocketAddr>) -> io::Result<Async<TcpStream>> {
+    type Attempt = Pin<Box<dyn Future<Output = (SocketAddr, io::Result<Async<TcpStream>>)>>>;
+
+    if addresses.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "host name did not resolve to any addresses",
+        ));
+    }
+
+    let mut pending = addresses.into_iter();
+    let mut attempts: Vec<Attempt> = Vec::new();
+    let mut errors = Vec::new();
+    let mut next_attempt = crate::Timer::after(web_time::Duration::ZERO);
+
+    std::future::poll_fn(|cx| {
+        loop {
+            // Start a new attempt if the last one has taken too long.
+            let start_next = next_attempt.poll(cx).is_ready();
+            if start_next {
+                match pending.next() {
+                    Some(address) => {
+                        attempts.push(Box::pin(async move {
+                            (address, Async::<TcpStream>::connect(address).await)
+                        }));
+                        next_attempt.set_after(CONNECTION_ATTEMPT_DELAY);
+                    }
+                    None => next_attempt.set_never(),
+                }
+            }
+
+            // Poll every attempt in flight.
+            let mut failed = false;
+            let mut i = 0;
+            while i < attempts.len() {
+                match attempts[i].as_mut().poll(cx) {
+                    Poll::Pending => i += 1,
+                    Poll::Ready((_, Ok(stream))) => return Poll::Ready(Ok(stream)),
+                    Poll::Ready((address, Err(err))) => {
+                        drop(attempts.swap_remove(i));
+                        errors.push((address, err));
+                        failed = true;
+                    }
+                }
+            }
+
+            if attempts.is_empty() && pending.len() == 0 {
+                // Every attempt has failed.
+                let errors = std::mem::take(&mut errors);
+                return Poll::Ready(Err(ConnectError { errors }.into()));
+            }
+
+            if failed {
+                // Start the next attempt immediately after a failure.
+                next_attempt.set_after(web_time::Duration::ZERO);
+            } else if !start_next {
+                return Poll::Pending;
+            }
+        }
+    })
+    .await
+}
+
+/// The error returned when every connection attempt to a host fails.
+#[derive(Debug)]
+pub struct ConnectError {
+    /// The errors for each address.
+    errors: Vec<(SocketAddr, io::Error)>,
+}
+
+impl ConnectError {
+    /// The addresses that were tried and the error for each one, in the order they failed.
+    #[inline]
+    pub fn errors(&self) -> &[(SocketAddr, io::Error)] {
+        &self.errors
+    }
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed to connect to any address")?;
+        for (i, (address, err)) in self.errors.iter().enumerate() {
+            let sep = if i == 0 { ": " } else { ", " };
+            write!(f, "{sep}{address} ({err})")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConnectError {}
+
+impl From<ConnectError> for io::Error {
+    #[inline]
+    fn from(err: ConnectError) -> Self {
+        let kind = err
+            .errors
+            .last()
+            .map_or(io::ErrorKind::NotFound, |(_, err)| err.kind());
+        io::Error::new(kind, err)
+    }
+}
+
+/// Open a non-blocking stream socket and connect it to an address.
+async fn connect<T: AsFd + From<OwnedFd>>(
+    family: AddressFamily,
+    address: &impl SocketAddrArg,
+) -> io::Result<Async<T>> {
+    use rustix::io::Errno;
+    use rustix::net::{SocketFlags, SocketType};
+
+    let socket = rustix::net::socket_with(
+        family,
+        SocketType::STREAM,
+        SocketFlags::NONBLOCK | SocketFlags::CLOEXEC,
+        None,
+    )?;
+
+    match rustix::net::connect(&socket, address) {
+        Ok(()) | Err(Errno::INPROGRESS | Errno::AGAIN) => {}
+        Err(err) => return Err(err.into()),
+    }
+
+    // The socket becomes writable once the connection is made or has failed.
+    let socket = Async::with_nonblocking(T::from(socket))?;
+    socket.writable().await?;
+    match rustix::net::sockopt::socket_error(socket.get_ref())? {
+        Ok(()) => Ok(socket),
+        Err(err) => Err(err.into()),
     }
 }
 
@@ -117,7 +619,7 @@ impl Async<UnixListener> {
     /// Bind this listener to a specific path.
     #[inline]
     pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
-        async_io::Async::<UnixListener>::bind(path.as_ref()).map(Self)
+        Async::new(UnixListener::bind(path)?)
     }
 }
 
@@ -125,20 +627,265 @@ impl Async<UnixStream> {
     /// Connect over UDS to the specific path.
     #[inline]
     pub async fn connect(path: impl AsRef<Path>) -> io::Result<Self> {
-        async_io::Async::<UnixStream>::connect(path.as_ref())
-            .await
-            .map(Self)
+        let address = rustix::net::SocketAddrUnix::new(path.as_ref())?;
+        connect(AddressFamily::UNIX, &address).await
     }
 
     /// Create a pair of joined sockets.
     #[inline]
     pub fn pair() -> io::Result<(Self, Self)> {
-        async_io::Async::<UnixStream>::pair().map(|(left, right)| (Self(left), Self(right)))
+        let (left, right) = UnixStream::pair()?;
+        Ok((Async::new(left)?, Async::new(right)?))
+    }
+
+    /// Send data along with a set of file descriptors.
+    ///
+    /// The file descriptors are duplicated into the peer process. They are sent along with
+    /// the first byte of `buf`, so `buf` should not be empty.
+    pub async fn send_with_fds(&self, buf: &[u8], fds: &[BorrowedFd<'_>]) -> io::Result<usize> {
+        use rustix::net::{SendAncillaryBuffer, SendAncillaryMessage, SendFlags};
+
+        let mut space = vec![MaybeUninit::uninit(); rustix::cmsg_space!(ScmRights(fds.len()))];
+        self.write_with(|socket| {
+            let mut control = SendAncillaryBuffer::new(&mut space);
+            if !fds.is_empty() && !control.push(SendAncillaryMessage::ScmRights(fds)) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "too many file descriptors to send",
+                ));
+            }
+
+            rustix::net::sendmsg(
+                socket,
+                &[IoSlice::new(buf)],
+                &mut control,
+                SendFlags::empty(),
+            )
+            .map_err(Into::into)
+        })
+        .await
+    }
+
+    /// Receive data along with any file descriptors sent with it.
+    ///
+    /// Received file descriptors are appended to `fds`. At most [`MAX_RECV_FDS`] file
+    /// descriptors are received at once. If more were sent, an error is returned: the
+    /// extra file descriptors are lost, and the ones that did arrive are closed. The data
+    /// is still consumed in that case.
+    #[inline]
+    pub async fn recv_with_fds(&self, buf: &mut [u8], fds: &mut Vec<OwnedFd>) -> io::Result<usize> {
+        self.recv_with_fds_at_most(buf, fds, MAX_RECV_FDS).await
+    }
+
+    /// Receive data along with at most `max_fds` file descriptors.
+    async fn recv_with_fds_at_most(
+        &self,
+        buf: &mut [u8],
+        fds: &mut Vec<OwnedFd>,
+        max_fds: usize,
+    ) -> io::Result<usize> {
+        use rustix::net::{RecvAncillaryBuffer, RecvAncillaryMessage, RecvFlags, ReturnFlags};
+
+        let mut space = vec![MaybeUninit::uninit(); rustix::cmsg_space!(ScmRights(max_fds))];
+        self.read_with(|socket| {
+            let mut control = RecvAncillaryBuffer::new(&mut space);
+            let received = rustix::net::recvmsg(
+                socket,
+                &mut [IoSliceMut::new(buf)],
+                &mut control,
+                RecvFlags::CMSG_CLOEXEC,
+            )?;
+
+            let mut received_fds = Vec::new();
+            for message in control.drain() {
+                if let RecvAncillaryMessage::ScmRights(message_fds) = message {
+                    received_fds.extend(message_fds);
+                }
+            }
+
+            // Some file descriptors did not fit into the control buffer. Those that did are
+            // dropped here, which closes them.
+            if received.flags.contains(ReturnFlags::CTRUNC) {
+                return Err(io::Error::other(
+                    "received more file descriptors than could be stored",
+                ));
+            }
+
+            fds.extend(received_fds);
+            Ok(received.bytes)
+        })
+        .await
+    }
+
+    /// Get the credentials of the process on the other end of this socket.
+    ///
+    /// These are the credentials of the peer at the time the connection was made.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn peer_cred(&self) -> io::Result<PeerCred> {
+        let cred = rustix::net::sockopt::socket_peercred(self.get_ref())?;
+
+        Ok(PeerCred {
+            pid: cred.pid.as_raw_nonzero().get() as u32,
+            uid: cred.uid.as_raw(),
+            gid: cred.gid.as_raw(),
+        })
+    }
+}
+
+/// The maximum number of file descriptors received by [`Async::<UnixStream>::recv_with_fds`].
+pub const MAX_RECV_FDS: usize = 253;
+
+/// The credentials of the process on the other end of a Unix socket.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PeerCred {
+    /// The process ID of the peer.
+    pub pid: u32,
+
+    /// The user ID of the peer.
+    pub uid: u32,
+
+    /// The group ID of the peer.
+    pub gid: u32,
+}
+
+impl Async<UdpSocket> {
+    /// Bind a UDP socket to a specific address.
+    #[inline]
+    pub fn bind(address: impl Into<SocketAddr>) -> io::Result<Self> {
+        Async::new(UdpSocket::bind(address.into())?)
+    }
+
+    /// Connect this socket to a remote address.
+    ///
+    /// This allows [`send`] and [`recv`] to be used, and filters out packets from other
+    /// addresses.
+    ///
+    /// [`send`]: Async::<UdpSocket>::send
+    /// [`recv`]: Async::<UdpSocket>::recv
+    #[inline]
+    pub fn connect(&self, address: impl Into<SocketAddr>) -> io::Result<()> {
+        self.get_ref().connect(address.into())
+    }
+
+    /// Receive a single datagram, returning the number of bytes read and the sender.
+    #[inline]
+    pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.read_with(|socket| socket.recv_from(buf)).await
+    }
+
+    /// Receive a single datagram without removing it from the queue.
+    #[inline]
+    pub async fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.read_with(|socket| socket.peek_from(buf)).await
+    }
+
+    /// Send a single datagram to an address, returning the number of bytes written.
+    #[inline]
+    pub async fn send_to(&self, buf: &[u8], address: impl Into<SocketAddr>) -> io::Result<usize> {
+        let address = address.into();
+        self.write_with(|socket| socket.send_to(buf, address)).await
+    }
+
+    /// Receive a single datagram from the connected address.
+    #[inline]
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_with(|socket| socket.recv(buf)).await
+    }
+
+    /// Receive a single datagram from the connected address without removing it from the
+    /// queue.
+    #[inline]
+    pub async fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_with(|socket| socket.peek(buf)).await
+    }
+
+    /// Send a single datagram to the connected address.
+    #[inline]
+    pub async fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.write_with(|socket| socket.send(buf)).await
+    }
+
+    /// Join an IPv4 multicast group on a specific interface.
+    #[inline]
+    pub fn join_multicast_v4(&self, multiaddr: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        self.get_ref().join_multicast_v4(&multiaddr, &interface)
+    }
+
+    /// Leave an IPv4 multicast group on a specific interface.
+    #[inline]
+    pub fn leave_multicast_v4(&self, multiaddr: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        self.get_ref().leave_multicast_v4(&multiaddr, &interface)
+    }
+
+    /// Join an IPv6 multicast group on the interface with the given index.
+    #[inline]
+    pub fn join_multicast_v6(&self, multiaddr: Ipv6Addr, interface: u32) -> io::Result<()> {
+        self.get_ref().join_multicast_v6(&multiaddr, interface)
+    }
+
+    /// Leave an IPv6 multicast group on the interface with the given index.
+    #[inline]
+    pub fn leave_multicast_v6(&self, multiaddr: Ipv6Addr, interface: u32) -> io::Result<()> {
+        self.get_ref().leave_multicast_v6(&multiaddr, interface)
+    }
+}
+
+impl Async<UnixDatagram> {
+    /// Bind a Unix datagram socket to a specific path.
+    #[inline]
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        Async::new(UnixDatagram::bind(path)?)
+    }
+
+    /// Create a Unix datagram socket that is not bound to any path.
+    #[inline]
+    pub fn unbound() -> io::Result<Self> {
+        Async::new(UnixDatagram::unbound()?)
+    }
+
+    /// Create a pair of connected sockets.
+    #[inline]
+    pub fn pair() -> io::Result<(Self, Self)> {
+        let (left, right) = UnixDatagram::pair()?;
+        Ok((Async::new(left)?, Async::new(right)?))
+    }
+
+    /// Connect this socket to the socket at a specific path.
+    #[inline]
+    pub fn connect(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.get_ref().connect(path)
+    }
+
+    /// Receive a single datagram, returning the number of bytes read and the sender.
+    #[inline]
+    pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, UnixSocketAddr)> {
+        self.read_with(|socket| socket.recv_from(buf)).await
+    }
+
+    /// Send a single datagram to the socket at a specific path.
+    #[inline]
+    pub async fn send_to(&self, buf: &[u8], path: impl AsRef<Path>) -> io::Result<usize> {
+        let path = path.as_ref();
+        self.write_with(|socket| socket.send_to(buf, path)).await
+    }
+
+    /// Receive a single datagram from the connected socket.
+    #[inline]
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_with(|socket| socket.recv(buf)).await
+    }
+
+    /// Send a single datagram to the connected socket.
+    #[inline]
+    pub async fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.write_with(|socket| socket.send(buf)).await
     }
 }
 
 /// The future to wait for this I/O source to be readable.
-pub struct Readable<'a, T>(async_io::Readable<'a, T>);
+pub struct Readable<'a, T>(ReadableInner<'a, T>);
 
 impl<T> fmt::Debug for Readable<'_, T> {
     #[inline]
@@ -154,12 +901,16 @@ impl<T> Future for Readable<'_, T> {
 
     #[inline]
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        Pin::new(&mut self.0).poll(cx)
+        match &mut self.0 {
+            ReadableInner::Io(ready) => Pin::new(ready).poll(cx),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            ReadableInner::Uring(source) => source.poll_ready(Interest::Readable, cx),
+        }
     }
 }
 
 /// The future to wait for this I/O source to be writable.
-pub struct Writable<'a, T>(async_io::Writable<'a, T>);
+pub struct Writable<'a, T>(WritableInner<'a, T>);
 
 impl<T> fmt::Debug for Writable<'_, T> {
     #[inline]
@@ -175,6 +926,58 @@ impl<T> Future for Writable<'_, T> {
 
     #[inline]
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        Pin::new(&mut self.0).poll(cx)
+        match &mut self.0 {
+            WritableInner::Io(ready) => Pin::new(ready).poll(cx),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            WritableInner::Uring(source) => source.poll_ready(Interest::Writable, cx),
+        }
+    }
+}
+
+/// Waiting for a source to become readable, with whatever it is registered with.
+enum ReadableInner<'a, T> {
+    /// With `async-io`.
+    Io(async_io::Readable<'a, T>),
+
+    /// With io_uring.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    Uring(&'a uring::Source),
+}
+
+/// Waiting for a source to become writable, with whatever it is registered with.
+enum WritableInner<'a, T> {
+    /// With `async-io`.
+    Io(async_io::Writable<'a, T>),
+
+    /// With io_uring.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    Uring(&'a uring::Source),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[crate::test]
+    async fn truncated_fds() -> io::Result<()> {
+        let (left, right) = Async::<UnixStream>::pair()?;
+        let fd = left.get_ref().as_fd();
+        left.send_with_fds(b"x", &[fd; 8]).await?;
+
+        // File descriptors that do not fit are reported instead of silently dropped. The
+        // control buffer is padded, so send well over the limit.
+        let mut buf = [0u8; 1];
+        let mut fds = Vec::new();
+        assert!(right
+            .recv_with_fds_at_most(&mut buf, &mut fds, 1)
+            .await
+            .is_err());
+        assert!(fds.is_empty());
+
+        // Everything arrives when there is enough room.
+        left.send_with_fds(b"y", &[fd, fd]).await?;
+        assert_eq!(right.recv_with_fds(&mut buf, &mut fds).await?, 1);
+        assert_eq!((buf, fds.len()), ([b'y'], 2));
+        Ok(())
     }
 }
@@ -0,0 +1,329 @@
+// MIT/Apache2 License
+
+//! Spawn child processes and wait for them using the reactor.
+//!
+//! This is available on open-source Unixes. On Linux, the exit of a child process is
+//! detected using a pidfd. On other platforms, or if pidfds are unavailable, `SIGCHLD`
+//! is used instead.
+
+use crate::platform::poll_io::Async;
+
+use futures_lite::future;
+use futures_lite::io::AsyncReadExt;
+use futures_lite::prelude::*;
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::process::{ChildStderr, ChildStdin, ChildStdout};
+
+#[doc(no_inline)]
+pub use std::process::{ExitStatus, Output, Stdio};
+
+/// A builder for spawning child processes.
+pub struct Command {
+    /// The underlying command.
+    inner: std::process::Command,
+
+    /// Whether `stdin` was configured.
+    stdin: bool,
+
+    /// Whether `stdout` was configured.
+    stdout: bool,
+
+    /// Whether `stderr` was configured.
+    stderr: bool,
+}
+
+impl fmt::Debug for Command {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl From<std::process::Command> for Command {
+    #[inline]
+    fn from(inner: std::process::Command) -> Self {
+        Self {
+            inner,
+            stdin: false,
+            stdout: false,
+            stderr: false,
+        }
+    }
+}
+
+impl Command {
+    /// Create a new command for launching `program`.
+    #[inline]
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        std::process::Command::new(program).into()
+    }
+
+    /// Add an argument to pass to the program.
+    #[inline]
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Self {
+        self.inner.arg(arg);
+        self
+    }
+
+    /// Add several arguments to pass to the program.
+    #[inline]
+    pub fn args<S: AsRef<OsStr>>(&mut self, args: impl IntoIterator<Item = S>) -> &mut Self {
+        self.inner.args(args);
+        self
+    }
+
+    /// Set an environment variable for the program.
+    #[inline]
+    pub fn env(&mut self, key: impl AsRef<OsStr>, val: impl AsRef<OsStr>) -> &mut Self {
+        self.inner.env(key, val);
+        self
+    }
+
+    /// Set several environment variables for the program.
+    #[inline]
+    pub fn envs<K, V>(&mut self, vars: impl IntoIterator<Item = (K, V)>) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.inner.envs(vars);
+        self
+    }
+
+    /// Remove an environment variable from the program's environment.
+    #[inline]
+    pub fn env_remove(&mut self, key: impl AsRef<OsStr>) -> &mut Self {
+        self.inner.env_remove(key);
+        self
+    }
+
+    /// Clear the program's environment.
+    #[inline]
+    pub fn env_clear(&mut self) -> &mut Self {
+        self.inner.env_clear();
+        self
+    }
+
+    /// Set the working directory for the program.
+    #[inline]
+    pub fn current_dir(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.inner.current_dir(dir);
+        self
+    }
+
+    /// Configure the program's standard input.
+    #[inline]
+    pub fn stdin(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.inner.stdin(cfg);
+        self.stdin = true;
+        self
+    }
+
+    /// Configure the program's standard output.
+    #[inline]
+    pub fn stdout(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.inner.stdout(cfg);
+        self.stdout = true;
+        self
+    }
+
+    /// Configure the program's standard error.
+    #[inline]
+    pub fn stderr(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.inner.stderr(cfg);
+        self.stderr = true;
+        self
+    }
+
+    /// Spawn the program as a child process.
+    ///
+    /// Standard I/O is inherited from this process unless configured otherwise.
+    #[inline]
+    pub fn spawn(&mut self) -> io::Result<Child> {
+        Child::new(self.inner.spawn()?)
+    }
+
+    /// Spawn the program and wait for it to exit.
+    ///
+    /// Standard I/O is inherited from this process unless configured otherwise.
+    #[inline]
+    pub async fn status(&mut self) -> io::Result<ExitStatus> {
+        self.spawn()?.status().await
+    }
+
+    /// Spawn the program and collect its output.
+    ///
+    /// Standard output and standard error are captured unless configured otherwise.
+    /// Standard input is null unless configured otherwise.
+    pub async fn output(&mut self) -> io::Result<Output> {
+        if !self.stdin {
+            self.inner.stdin(Stdio::null());
+        }
+        if !self.stdout {
+            self.inner.stdout(Stdio::piped());
+        }
+        if !self.stderr {
+            self.inner.stderr(Stdio::piped());
+        }
+
+        self.spawn()?.output().await
+    }
+}
+
+/// A spawned child process.
+///
+/// Dropping a `Child` does not kill the process.
+pub struct Child {
+    /// The standard input of the child, if piped.
+    pub stdin: Option<Async<ChildStdin>>,
+
+    /// The standard output of the child, if piped.
+    pub stdout: Option<Async<ChildStdout>>,
+
+    /// The standard error of the child, if piped.
+    pub stderr: Option<Async<ChildStderr>>,
+
+    /// The underlying child process.
+    child: std::process::Child,
+
+    /// The source used to watch for the process exiting.
+    watcher: Option<ExitWatcher>,
+}
+
+impl fmt::Debug for Child {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Child")
+            .field("id", &self.child.id())
+            .field("stdin", &self.stdin)
+            .field("stdout", &self.stdout)
+            .field("stderr", &self.stderr)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Child {
+    /// Wrap a `std` child process.
+    fn new(mut child: std::process::Child) -> io::Result<Self> {
+        Ok(Self {
+            stdin: child.stdin.take().map(Async::new).transpose()?,
+            stdout: child.stdout.take().map(Async::new).transpose()?,
+            stderr: child.stderr.take().map(Async::new).transpose()?,
+            child,
+            watcher: None,
+        })
+    }
+
+    /// Get the OS-assigned process identifier of the child.
+    #[inline]
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Forcibly kill the child process.
+    #[inline]
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.child.kill()
+    }
+
+    /// Get the exit status of the child if it has already exited.
+    #[inline]
+    pub fn try_status(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.child.try_wait()
+    }
+
+    /// Wait for the child process to exit.
+    ///
+    /// The child's standard input is closed before waiting to prevent deadlocks.
+    pub async fn status(&mut self) -> io::Result<ExitStatus> {
+        drop(self.stdin.take());
+
+        loop {
+            // See if the child has already exited.
+            if let Some(status) = self.child.try_wait()? {
+                return Ok(status);
+            }
+
+            // Wait for the child to exit.
+            match &mut self.watcher {
+                Some(watcher) => watcher.wait().await?,
+                None => self.watcher = Some(ExitWatcher::new(&self.child)?),
+            }
+        }
+    }
+
+    /// Wait for the child to exit and collect its output.
+    ///
+    /// The child's standard input is closed before waiting to prevent deadlocks.
+    pub async fn output(mut self) -> io::Result<Output> {
+        drop(self.stdin.take());
+
+        let stdout = read_all(self.stdout.take());
+        let stderr = read_all(self.stderr.take());
+        let (stdout, stderr) = future::zip(stdout, stderr).await;
+        let status = self.status().await?;
+
+        Ok(Output {
+            status,
+            stdout: stdout?,
+            stderr: stderr?,
+        })
+    }
+}
+
+/// Read a piped stream to the end.
+async fn read_all<T>(io: Option<Async<T>>) -> io::Result<Vec<u8>>
+where
+    Async<T>: futures_io::AsyncRead + Unpin,
+{
+    let mut buf = Vec::new();
+    if let Some(mut io) = io {
+        io.read_to_end(&mut buf).await?;
+    }
+    Ok(buf)
+}
+
+/// Source of notifications for a process exiting.
+enum ExitWatcher {
+    /// A pidfd that becomes readable when the process exits.
+    #[cfg(target_os = "linux")]
+    PidFd(Async<std::os::unix::io::OwnedFd>),
+
+    /// A stream of `SIGCHLD` signals.
+    Sigchld(async_signal::Signals),
+}
+
+impl ExitWatcher {
+    /// Create a new watcher for a child process.
+    fn new(child: &std::process::Child) -> io::Result<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            use rustix::process::{pidfd_open, Pid, PidfdFlags};
+
+            let pid = Pid::from_raw(child.id() as i32)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+            if let Ok(pidfd) = pidfd_open(pid, PidfdFlags::empty()) {
+                return Async::new(pidfd).map(Self::PidFd);
+            }
+        }
+
+        let _ = child;
+        async_signal::Signals::new([async_signal::Signal::Child]).map(Self::Sigchld)
+    }
+
+    /// Wait for a possible process exit.
+    async fn wait(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Self::PidFd(pidfd) => pidfd.readable().await,
+            Self::Sigchld(signals) => match signals.next().await {
+                Some(result) => result.map(|_| ()),
+                None => Err(io::ErrorKind::UnexpectedEof.into()),
+            },
+        }
+    }
+}
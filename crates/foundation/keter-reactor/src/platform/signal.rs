@@ -0,0 +1,175 @@
+// MIT/Apache2 License
+
+//! Handle Unix signals using the reactor.
+//!
+//! This is available on open-source Unixes.
+
+use crate::{Reactor, ReactorBuilder};
+
+use futures_core::stream::Stream;
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::io;
+use std::os::unix::io::{AsFd, BorrowedFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+#[doc(no_inline)]
+pub use async_signal::Signal;
+
+/// A stream of Unix signals received by this process.
+///
+/// Registering a signal prevents its default behavior from occurring. For example,
+/// registering [`Signal::Int`] means that `Ctrl+C` no longer terminates the process.
+pub struct Signals(async_signal::Signals);
+
+impl fmt::Debug for Signals {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Signals").field(&self.0).finish()
+    }
+}
+
+impl Signals {
+    /// Create a new stream that listens for a set of signals.
+    #[inline]
+    pub fn new<B: Borrow<Signal>>(signals: impl IntoIterator<Item = B>) -> io::Result<Self> {
+        async_signal::Signals::new(signals).map(Self)
+    }
+
+    /// Start listening for more signals.
+    ///
+    /// Signals that are already being listened for are ignored.
+    #[inline]
+    pub fn add_signals<B: Borrow<Signal>>(
+        &mut self,
+        signals: impl IntoIterator<Item = B>,
+    ) -> io::Result<()> {
+        self.0.add_signals(signals)
+    }
+
+    /// Stop listening for a set of signals.
+    ///
+    /// Signals that are not being listened for are ignored.
+    #[inline]
+    pub fn remove_signals<B: Borrow<Signal>>(
+        &mut self,
+        signals: impl IntoIterator<Item = B>,
+    ) -> io::Result<()> {
+        self.0.remove_signals(signals)
+    }
+}
+
+impl AsFd for Signals {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl Unpin for Signals {}
+
+impl Stream for Signals {
+    type Item = io::Result<Signal>;
+
+    #[inline]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0).poll_next(cx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl Stream for &Signals {
+    type Item = io::Result<Signal>;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut &self.0).poll_next(cx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// Extension trait that allows the [`Reactor`] to exit when it receives a signal.
+///
+/// [`Reactor`]: crate::Reactor
+pub trait ReactorExt: Sized + crate::platform::sealed::Sealed {
+    /// Exit the reactor when `SIGINT` or `SIGTERM` is received.
+    ///
+    /// This takes the same path as [`exit()`], so the future passed to
+    /// [`Reactor::block_on`] is dropped and the reactor returns successfully. If waiting
+    /// for the signals fails, [`Reactor::block_on`] returns the error instead.
+    ///
+    /// [`exit()`]: crate::exit
+    /// [`Reactor::block_on`]: crate::Reactor::block_on
+    fn with_exit_on_signal(self) -> Self;
+}
+
+impl ReactorExt for Reactor {
+    #[inline]
+    fn with_exit_on_signal(mut self) -> Self {
+        self.settings.exit_on_signal = true;
+        self
+    }
+}
+
+/// Extension trait that allows a [`ReactorBuilder`] to build reactors that exit when they
+/// receive a signal.
+///
+/// [`ReactorBuilder`]: crate::ReactorBuilder
+pub trait ReactorBuilderExt: Sized + crate::platform::sealed::Sealed {
+    /// Set whether the reactor exits when `SIGINT` or `SIGTERM` is received.
+    ///
+    /// See [`ReactorExt::with_exit_on_signal`] for more information. This is off by
+    /// default.
+    fn exit_on_signal(self, exit_on_signal: bool) -> Self;
+}
+
+impl ReactorBuilderExt for ReactorBuilder {
+    #[inline]
+    fn exit_on_signal(mut self, exit_on_signal: bool) -> Self {
+        self.settings.exit_on_signal = exit_on_signal;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn exit_on_signal() {
+        use crate::platform::any_thread::ReactorExt as _;
+        use crate::platform::instantiation::ReactorExt as _;
+        use rustix::process::{getpid, kill_process, Signal};
+        use std::process::Command;
+
+        // The signal goes to the whole process, so send it to a copy of this test instead.
+        const CHILD: &str = "KETER_REACTOR_SIGNAL_CHILD";
+        if std::env::var_os(CHILD).is_none() {
+            let status = Command::new(std::env::current_exe().unwrap())
+                .args(["--exact", "platform::signal::tests::exit_on_signal"])
+                .env(CHILD, "1")
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
+        }
+
+        let reactor = Reactor::new().with_any_thread().with_exit_on_signal();
+        let result = reactor.block_on(async {
+            kill_process(getpid(), Signal::TERM).unwrap();
+            std::future::pending().await
+        });
+        assert!(result.unwrap().is_success());
+    }
+}
@@ -0,0 +1,244 @@
+// MIT/Apache2 License
+
+//! Drive X11 connections using the reactor.
+//!
+//! This is available on open-source Unixes with the `x11` feature enabled. It uses the
+//! pure-Rust connection from [`x11rb`], registered with the reactor as an
+//! [`EventSource`], so requests are flushed before the reactor sleeps and events that
+//! `x11rb` has already read into memory are delivered without waiting on the socket.
+//!
+//! Requests are sent through [`Connection::get_ref`] using the traits in
+//! [`x11rb::protocol`]. Waiting on a reply blocks the thread until the server answers, so
+//! prefer requests that do not need one inside of the reactor.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! use keter_reactor::platform::x11::Connection;
+//! use keter_reactor::platform::x11::x11rb::protocol::Event;
+//!
+//! # async fn f() -> std::io::Result<()> {
+//! let connection = Connection::connect(None)?;
+//!
+//! loop {
+//!     match connection.event().await? {
+//!         Event::Expose(_) => println!("redraw"),
+//!         event => println!("{event:?}"),
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! [`EventSource`]: crate::platform::event_source::EventSource
+
+use crate::platform::event_source::{register, EventSource, Registration};
+
+use futures_core::stream::Stream;
+use x11rb::connection::Connection as _;
+use x11rb::errors::{ConnectError, ConnectionError};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::os::unix::io::{AsFd, BorrowedFd};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+#[doc(no_inline)]
+pub use x11rb;
+
+/// A connection to an X11 server, driven by the reactor.
+pub struct Connection {
+    /// The connection to the server.
+    conn: Rc<RustConnection>,
+
+    /// The index of the default screen.
+    screen: usize,
+
+    /// The connection's registration with the reactor.
+    registration: Registration<Source>,
+}
+
+impl fmt::Debug for Connection {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Connection")
+            .field("screen", &self.screen)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Connection {
+    /// Connect to an X11 server.
+    ///
+    /// If `display` is `None`, the `DISPLAY` environment variable is used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no reactor is running on this thread.
+    pub fn connect(display: Option<&str>) -> io::Result<Self> {
+        let (conn, screen) = RustConnection::connect(display).map_err(connect_error)?;
+        Self::from_connection(conn, screen)
+    }
+
+    /// Register an existing connection to an X11 server with the reactor.
+    ///
+    /// `screen` is the index of the default screen.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no reactor is running on this thread.
+    pub fn from_connection(conn: RustConnection, screen: usize) -> io::Result<Self> {
+        let conn = Rc::new(conn);
+        let registration = register(Source {
+            conn: conn.clone(),
+            events: VecDeque::new(),
+            waker: None,
+        })?;
+
+        Ok(Self {
+            conn,
+            screen,
+            registration,
+        })
+    }
+
+    /// Get the underlying connection, to send requests with.
+    #[inline]
+    pub fn get_ref(&self) -> &RustConnection {
+        &self.conn
+    }
+
+    /// Get the index of the default screen.
+    #[inline]
+    pub fn screen_num(&self) -> usize {
+        self.screen
+    }
+
+    /// Poll for the next event from the server.
+    pub fn poll_event(&self, cx: &mut Context<'_>) -> Poll<io::Result<Event>> {
+        let mut source = self.registration.get_mut();
+        if let Some(event) = source.events.pop_front() {
+            return Poll::Ready(Ok(event));
+        }
+
+        // Pick up any event that was read since the reactor last checked.
+        match self.conn.poll_for_event() {
+            Ok(Some(event)) => return Poll::Ready(Ok(event)),
+            Ok(None) => {}
+            Err(error) => return Poll::Ready(Err(connection_error(error))),
+        }
+
+        source.waker = Some(cx.waker().clone());
+        drop(source);
+        self.registration.poll_error(cx).map(Err)
+    }
+
+    /// Wait for the next event from the server.
+    #[inline]
+    pub async fn event(&self) -> io::Result<Event> {
+        futures_lite::future::poll_fn(|cx| self.poll_event(cx)).await
+    }
+}
+
+impl AsFd for Connection {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.conn.stream().as_fd()
+    }
+}
+
+impl Stream for Connection {
+    type Item = io::Result<Event>;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_event(cx).map(Some)
+    }
+}
+
+impl Stream for &Connection {
+    type Item = io::Result<Event>;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_event(cx).map(Some)
+    }
+}
+
+/// The connection as seen by the reactor.
+struct Source {
+    /// The connection to the server.
+    conn: Rc<RustConnection>,
+
+    /// Events read by the reactor that have not been delivered yet.
+    events: VecDeque<Event>,
+
+    /// Woken when events are read.
+    waker: Option<Waker>,
+}
+
+impl Source {
+    /// Move the events `x11rb` has read into the queue.
+    #[inline]
+    fn read(&mut self) -> io::Result<()> {
+        while let Some(event) = self.conn.poll_for_event().map_err(connection_error)? {
+            self.events.push_back(event);
+        }
+        Ok(())
+    }
+}
+
+impl AsFd for Source {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.conn.stream().as_fd()
+    }
+}
+
+impl EventSource for Source {
+    #[inline]
+    fn prepare(&mut self) -> io::Result<bool> {
+        self.read()?;
+
+        // Events nobody is waiting for stay queued without keeping the reactor awake.
+        Ok(!self.events.is_empty() && self.waker.is_some())
+    }
+
+    #[inline]
+    fn dispatch(&mut self, _readable: bool) -> io::Result<()> {
+        self.read()?;
+        if !self.events.is_empty() {
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.conn.flush().map_err(connection_error)
+    }
+}
+
+/// Convert an error from an established connection into an I/O error.
+#[inline]
+fn connection_error(error: ConnectionError) -> io::Error {
+    match error {
+        ConnectionError::IoError(error) => error,
+        error => io::Error::other(error),
+    }
+}
+
+/// Convert an error from connecting to the server into an I/O error.
+#[inline]
+fn connect_error(error: ConnectError) -> io::Error {
+    match error {
+        ConnectError::IoError(error) => error,
+        error => io::Error::other(error),
+    }
+}
@@ -0,0 +1,160 @@
+// MIT/Apache2 License
+
+//! Handles for sending work into the reactor from other threads.
+
+use async_channel::{Receiver, Sender};
+
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::stream::Stream;
+
+/// A closure posted to the reactor.
+type Posted = Box<dyn FnOnce() + Send + 'static>;
+
+/// The reactor's end of the queue of posted closures.
+pub(crate) struct PostQueue {
+    /// Sender used to create new proxies.
+    sender: Sender<Posted>,
+
+    /// Receiver for closures posted by proxies.
+    receiver: Receiver<Posted>,
+}
+
+impl PostQueue {
+    /// Create a new, empty queue.
+    #[inline]
+    pub(crate) fn new() -> Self {
+        let (sender, receiver) = async_channel::unbounded();
+        Self { sender, receiver }
+    }
+
+    /// Create a new proxy for this queue.
+    #[inline]
+    pub(crate) fn proxy<T>(&self) -> (Proxy<T>, Messages<T>) {
+        let (sender, receiver) = async_channel::unbounded();
+        let proxy = Proxy {
+            messages: sender,
+            posted: self.sender.clone(),
+        };
+
+        (proxy, Messages(Box::pin(receiver)))
+    }
+
+    /// Run closures as they are posted.
+    pub(crate) async fn run(&self) -> Infallible {
+        loop {
+            // We hold a sender, so the channel never closes.
+            if let Ok(f) = self.receiver.recv().await {
+                f();
+            }
+        }
+    }
+}
+
+/// A handle for sending work into a [`Reactor`] from any thread.
+///
+/// [`Reactor`]: crate::Reactor
+pub struct Proxy<T> {
+    /// Sender for typed messages.
+    messages: Sender<T>,
+
+    /// Sender for closures.
+    posted: Sender<Posted>,
+}
+
+impl<T> fmt::Debug for Proxy<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Proxy").finish_non_exhaustive()
+    }
+}
+
+impl<T> Clone for Proxy<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            messages: self.messages.clone(),
+            posted: self.posted.clone(),
+        }
+    }
+}
+
+impl<T> Proxy<T> {
+    /// Send a message to the [`Messages`] stream for this proxy.
+    ///
+    /// Returns an error if the [`Messages`] stream has been dropped.
+    #[inline]
+    pub fn send(&self, message: T) -> Result<(), SendError<T>> {
+        self.messages
+            .try_send(message)
+            .map_err(|err| SendError(err.into_inner()))
+    }
+
+    /// Run a closure on the thread running the reactor.
+    ///
+    /// Returns an error if the reactor has been dropped.
+    #[inline]
+    pub fn post(&self, f: impl FnOnce() + Send + 'static) -> Result<(), SendError<()>> {
+        self.posted.try_send(Box::new(f)).map_err(|_| SendError(()))
+    }
+
+    /// Wake up the reactor, causing it to run another iteration of its loop.
+    #[inline]
+    pub fn wake(&self) -> Result<(), SendError<()>> {
+        self.post(|| {})
+    }
+}
+
+/// The stream of messages sent through a [`Proxy`].
+pub struct Messages<T>(Pin<Box<Receiver<T>>>);
+
+impl<T> fmt::Debug for Messages<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Messages")
+            .field("len", &self.0.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Unpin for Messages<T> {}
+
+impl<T> Stream for Messages<T> {
+    type Item = T;
+
+    #[inline]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.as_mut().poll_next(cx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// An error returned when the receiving end of a [`Proxy`] is gone.
+///
+/// Contains the value that could not be sent.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("sending into a closed reactor")
+    }
+}
+
+impl<T> Error for SendError<T> {}
@@ -0,0 +1,178 @@
+// MIT/Apache2 License
+
+//! Cleanup hooks that run when the reactor stops.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::task::Poll;
+
+use web_time::Duration;
+
+/// A registered cleanup future.
+type Hook = Pin<Box<dyn Future<Output = ()> + 'static>>;
+
+/// How long cleanup hooks are given to run by default.
+pub(crate) const DEFAULT_DEADLINE: Duration = Duration::from_secs(5);
+
+thread_local! {
+    /// Hooks registered on this thread, one list for each running reactor, innermost last.
+    ///
+    /// The first list holds hooks registered while no reactor is running.
+    static HOOKS: RefCell<Vec<Vec<Hook>>> = RefCell::new(vec![Vec::new()]);
+}
+
+/// Register a cleanup hook for the innermost reactor on this thread.
+#[inline]
+pub(crate) fn register(hook: impl Future<Output = ()> + 'static) {
+    HOOKS.with(|hooks| {
+        if let Some(frame) = hooks.borrow_mut().last_mut() {
+            frame.push(Box::pin(hook));
+        }
+    });
+}
+
+/// The hooks registered during a reactor run.
+pub(crate) struct HookFrame(RefCell<Vec<Hook>>);
+
+impl HookFrame {
+    /// Create the hooks for a new reactor run.
+    ///
+    /// The outermost reactor also takes the hooks registered before it started.
+    #[inline]
+    pub(crate) fn new() -> Self {
+        let hooks = HOOKS.with(|hooks| match &mut hooks.borrow_mut()[..] {
+            [before] => mem::take(before),
+            _ => Vec::new(),
+        });
+
+        Self(RefCell::new(hooks))
+    }
+
+    /// Collect hooks into this frame until the scope is dropped.
+    #[inline]
+    pub(crate) fn enter(&self) -> HookScope<'_> {
+        let frame = mem::take(&mut *self.0.borrow_mut());
+        HOOKS.with(|hooks| hooks.borrow_mut().push(frame));
+        HookScope(self)
+    }
+}
+
+/// Stops collecting hooks into a [`HookFrame`] when dropped.
+pub(crate) struct HookScope<'a>(&'a HookFrame);
+
+impl Drop for HookScope<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(frame) = HOOKS.with(|hooks| hooks.borrow_mut().pop()) {
+            *self.0 .0.borrow_mut() = frame;
+        }
+    }
+}
+
+/// Run all registered hooks concurrently until they finish or the deadline passes.
+///
+/// Hooks that are registered by other hooks are run as well. Hooks that are still running
+/// once the deadline passes are dropped.
+pub(crate) async fn run(deadline: Duration) {
+    // The deadline is in real time, even if the reactor uses a virtual clock.
+    let mut timer = {
+        let _real = crate::time::ClockScope::enter(None);
+        crate::Timer::after(deadline)
+    };
+    let mut running = Vec::<Hook>::new();
+
+    futures_lite::future::poll_fn(|cx| {
+        // Poll the hooks that were already running.
+        running.retain_mut(|hook| hook.as_mut().poll(cx).is_pending());
+
+        // Start any new hooks, including ones registered by the hooks we just polled.
+        loop {
+            let new = HOOKS.with(|hooks| {
+                hooks
+                    .borrow_mut()
+                    .last_mut()
+                    .map(mem::take)
+                    .unwrap_or_default()
+            });
+            if new.is_empty() {
+                break;
+            }
+
+            running.extend(
+                new.into_iter()
+                    .filter_map(|mut hook| hook.as_mut().poll(cx).is_pending().then_some(hook)),
+            );
+        }
+
+        if running.is_empty() {
+            return Poll::Ready(());
+        }
+
+        // Give up on the remaining hooks once the deadline passes.
+        Pin::new(&mut timer).poll(cx)
+    })
+    .await;
+}
+
+#[cfg(all(test, unix, not(target_vendor = "apple"), not(target_os = "android")))]
+mod tests {
+    use super::*;
+    use crate::{exit_with, on_exit, test_reactor, Timer};
+
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn exit_hooks() {
+        let ran = Rc::new(Cell::new(0));
+        let finished = test_reactor(|builder| builder)
+            .block_on({
+                let ran = ran.clone();
+                async move {
+                    let inner = ran.clone();
+                    on_exit(async move {
+                        Timer::after(Duration::from_millis(10)).await;
+                        inner.set(inner.get() + 1);
+
+                        // Hooks registered by hooks also run.
+                        let inner = inner.clone();
+                        on_exit(async move { inner.set(inner.get() + 1) });
+                    });
+
+                    exit_with(2).await
+                }
+            })
+            .unwrap();
+        assert_eq!(finished.exit_code(), 2);
+        assert_eq!(ran.get(), 2);
+
+        // Hooks also run when the future completes on its own.
+        let ran = Rc::new(Cell::new(false));
+        test_reactor(|builder| builder)
+            .__block_on_result({
+                let ran = ran.clone();
+                async move {
+                    on_exit(async move { ran.set(true) });
+                    Ok(())
+                }
+            })
+            .unwrap();
+        assert!(ran.get());
+    }
+
+    #[test]
+    fn exit_hook_deadline() {
+        use std::time::Instant;
+
+        let start = Instant::now();
+        test_reactor(|builder| builder.exit_deadline(Duration::from_millis(50)))
+            .__block_on_result(async {
+                on_exit(std::future::pending());
+                Ok(())
+            })
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,435 @@
+// MIT/Apache2 License
+
+//! Deterministic simulation of the reactor's environment.
+//!
+//! A reactor built with [`ReactorBuilder::simulation`] runs against a [`Simulation`]
+//! instead of the outside world:
+//!
+//! - Timers run against a [`VirtualClock`] that skips ahead whenever the reactor is idle.
+//! - The [`TcpListener`], [`TcpStream`] and [`UdpSocket`] types in this module talk over
+//!   an in-memory network owned by the simulation.
+//! - Wakeups from timers and the network are not delivered right away. Instead, the
+//!   reactor delivers them one at a time whenever it is idle, in an order picked by a
+//!   seeded random number generator.
+//!
+//! Every choice made is recorded in a [`Schedule`]. Running the same program with the
+//! same seed, or with [`Simulation::replay`], reproduces the same interleaving of tasks.
+//! This makes it possible to search for rare interleavings by trying many seeds, and then
+//! to reproduce a failing one.
+//!
+//! Real I/O, such as [`poll_io`], is not simulated: those sockets always use the operating
+//! system, inside of a simulation or not. Programs should be written against the simulated
+//! sockets in tests to be deterministic. Unix sockets have no simulated counterpart. The
+//! simulated network is meant to be used from the thread running the simulation.
+//!
+//! [`ReactorBuilder::simulation`]: crate::ReactorBuilder::simulation
+//! [`VirtualClock`]: crate::time::VirtualClock
+//! [`poll_io`]: crate::platform
+
+mod net;
+
+pub use net::{TcpListener, TcpStream, UdpSocket};
+
+use crate::time::VirtualClock;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Waker;
+
+thread_local! {
+    /// The simulation of the innermost reactor running on this thread, if it has one.
+    static CURRENT: RefCell<Option<Simulation>> = const { RefCell::new(None) };
+}
+
+/// Get the simulation of the innermost reactor running on this thread.
+#[inline]
+fn current() -> Option<Simulation> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Wake a task, letting the current simulation decide when.
+#[inline]
+pub(crate) fn wake(waker: Waker) {
+    match current() {
+        Some(sim) => sim.defer(waker),
+        None => waker.wake(),
+    }
+}
+
+/// Deliver the next wakeup picked by the current simulation.
+///
+/// Returns `true` if a task was woken.
+#[inline]
+pub(crate) fn wake_next() -> bool {
+    match current() {
+        Some(sim) => sim.wake_next(),
+        None => false,
+    }
+}
+
+/// Sets the simulation for this thread until dropped.
+pub(crate) struct SimScope(Option<Simulation>);
+
+impl SimScope {
+    /// Use the given simulation until the scope is dropped.
+    #[inline]
+    pub(crate) fn enter(sim: Option<Simulation>) -> Self {
+        Self(CURRENT.with(|current| current.replace(sim)))
+    }
+}
+
+impl Drop for SimScope {
+    #[inline]
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+/// A deterministic environment for running a reactor in.
+///
+/// See the [module-level documentation](self) for more information.
+#[derive(Clone)]
+pub struct Simulation(Arc<Shared>);
+
+/// The shared state of a [`Simulation`].
+struct Shared {
+    /// The clock that the simulation's timers run against.
+    clock: VirtualClock,
+
+    /// The mutable state of the simulation.
+    state: Mutex<State>,
+}
+
+/// The mutable state of a [`Simulation`].
+struct State {
+    /// The seed the simulation started with.
+    seed: u64,
+
+    /// Picks the order of wakeups.
+    rng: Rng,
+
+    /// Choices left to replay before using the random number generator.
+    replay: VecDeque<u32>,
+
+    /// The choices made so far.
+    recorded: Vec<u32>,
+
+    /// Wakeups that have not been delivered yet.
+    pending: Vec<Waker>,
+
+    /// The in-memory network.
+    network: net::Network,
+}
+
+impl fmt::Debug for Simulation {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state();
+        f.debug_struct("Simulation")
+            .field("seed", &state.seed)
+            .field("choices", &state.recorded.len())
+            .field("pending", &state.pending.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Simulation {
+    /// Create a new simulation that picks the order of wakeups using the given seed.
+    #[inline]
+    pub fn new(seed: u64) -> Self {
+        Self::with_choices(seed, VecDeque::new())
+    }
+
+    /// Create a new simulation that makes the same choices as a recorded schedule.
+    ///
+    /// Once the recorded choices run out, the simulation continues as if it had been
+    /// created with the schedule's seed.
+    ///
+    /// # Panics
+    ///
+    /// The simulation panics if the program diverges from the schedule, such that a
+    /// recorded choice is no longer possible.
+    #[inline]
+    pub fn replay(schedule: &Schedule) -> Self {
+        Self::with_choices(schedule.seed, schedule.choices.iter().copied().collect())
+    }
+
+    /// Create a new simulation with choices to replay.
+    fn with_choices(seed: u64, replay: VecDeque<u32>) -> Self {
+        Self(Arc::new(Shared {
+            clock: VirtualClock::new().with_auto_advance(true),
+            state: Mutex::new(State {
+                seed,
+                rng: Rng(seed),
+                replay,
+                recorded: Vec::new(),
+                pending: Vec::new(),
+                network: net::Network::default(),
+            }),
+        }))
+    }
+
+    /// Get the seed this simulation started with.
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.state().seed
+    }
+
+    /// Get the clock that the simulation's timers run against.
+    #[inline]
+    pub fn clock(&self) -> &VirtualClock {
+        &self.0.clock
+    }
+
+    /// Get the choices made by the simulation so far.
+    #[inline]
+    pub fn schedule(&self) -> Schedule {
+        let state = self.state();
+        Schedule {
+            seed: state.seed,
+            choices: state.recorded.clone(),
+        }
+    }
+
+    /// Hold a wakeup until the simulation picks it.
+    #[inline]
+    fn defer(&self, waker: Waker) {
+        self.state().pending.push(waker);
+    }
+
+    /// Deliver one of the pending wakeups.
+    fn wake_next(&self) -> bool {
+        let waker = {
+            let mut state = self.state();
+            if state.pending.is_empty() {
+                return false;
+            }
+
+            let len = state.pending.len();
+            let index = state.pick(len);
+            state.pending.swap_remove(index)
+        };
+
+        waker.wake();
+        true
+    }
+
+    /// Lock the state of the simulation.
+    #[inline]
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.0.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl State {
+    /// Pick an index below `len`, recording the choice.
+    fn pick(&mut self, len: usize) -> usize {
+        // There is nothing to choose between.
+        if len == 1 {
+            return 0;
+        }
+
+        // Draw a number even when replaying, so that a replay continues like the original.
+        let random = (self.rng.next() % len as u64) as u32;
+        let choice = match self.replay.pop_front() {
+            Some(choice) => {
+                assert!(
+                    (choice as usize) < len,
+                    "simulation diverged from its schedule: choice {} of {} after {} choices",
+                    choice,
+                    len,
+                    self.recorded.len()
+                );
+                choice
+            }
+            None => random,
+        };
+
+        self.recorded.push(choice);
+        choice as usize
+    }
+}
+
+/// The choices made by a [`Simulation`].
+///
+/// This can be printed and parsed back so that a failing run can be replayed through
+/// [`Simulation::replay`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    /// The seed the simulation started with.
+    seed: u64,
+
+    /// The choices that were made, in order.
+    choices: Vec<u32>,
+}
+
+impl Schedule {
+    /// Get the seed the simulation started with.
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Get the choices that were made, in order.
+    #[inline]
+    pub fn choices(&self) -> &[u32] {
+        &self.choices
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.seed)?;
+        for (i, choice) in self.choices.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{choice}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = ParseScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (seed, choices) = s.split_once(':').ok_or(ParseScheduleError(()))?;
+        let seed = seed.trim().parse().map_err(|_| ParseScheduleError(()))?;
+        let choices = match choices.trim() {
+            "" => Vec::new(),
+            choices => choices
+                .split(',')
+                .map(|choice| choice.trim().parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| ParseScheduleError(()))?,
+        };
+
+        Ok(Self { seed, choices })
+    }
+}
+
+/// The error returned when a [`Schedule`] could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseScheduleError(());
+
+impl fmt::Display for ParseScheduleError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid simulation schedule")
+    }
+}
+
+impl Error for ParseScheduleError {}
+
+/// A small, fast random number generator (SplitMix64).
+struct Rng(u64);
+
+impl Rng {
+    /// Get the next random number.
+    #[inline]
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(all(test, unix, not(target_vendor = "apple"), not(target_os = "android")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulation() {
+        use crate::{spawn_local, test_reactor, Result, Timer};
+        use futures_lite::{AsyncReadExt, AsyncWriteExt};
+        use std::collections::HashSet;
+        use web_time::Duration;
+
+        // Several clients race to send their IDs to a server.
+        fn run(sim: Simulation) -> Vec<u8> {
+            let reactor = test_reactor(|builder| builder.simulation(sim));
+
+            let mut order = Vec::new();
+            reactor
+                .__block_on_result(async {
+                    let listener = TcpListener::bind(([10, 0, 0, 1], 80))?;
+                    let addr = listener.local_addr()?;
+
+                    let clients = (0..4u8)
+                        .map(|id| {
+                            spawn_local(async move {
+                                Timer::after(Duration::from_secs(1)).await;
+                                let mut stream = TcpStream::connect(addr).await?;
+                                stream.write_all(&[id]).await?;
+                                Result::Ok(())
+                            })
+                        })
+                        .collect::<Vec<_>>();
+
+                    for _ in 0..4 {
+                        let (mut stream, peer) = listener.accept().await?;
+                        assert_eq!(stream.peer_addr()?, peer);
+                        let mut id = [0];
+                        stream.read_exact(&mut id).await?;
+                        order.push(id[0]);
+                    }
+                    for client in clients {
+                        client.await?;
+                    }
+
+                    Ok(())
+                })
+                .unwrap();
+            order
+        }
+
+        // The same seed always produces the same interleaving.
+        let sim = Simulation::new(7);
+        let order = run(sim.clone());
+        assert_eq!(run(Simulation::new(7)), order);
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, [0, 1, 2, 3]);
+
+        // Recorded schedules can be written down and replayed.
+        let schedule = sim.schedule();
+        assert_eq!(schedule.seed(), 7);
+        let parsed = schedule.to_string().parse::<Schedule>().unwrap();
+        assert_eq!(parsed, schedule);
+        assert_eq!(run(Simulation::replay(&parsed)), order);
+
+        // Different seeds explore different interleavings.
+        let orders = (0..16)
+            .map(|seed| run(Simulation::new(seed)))
+            .collect::<HashSet<_>>();
+        assert!(orders.len() > 1);
+
+        // Datagrams are delivered between simulated sockets, truncated to fit.
+        test_reactor(|builder| builder.simulation(Simulation::new(0)))
+            .__block_on_result(async {
+                let left = UdpSocket::bind(([10, 0, 0, 1], 0))?;
+                let right = UdpSocket::bind(([10, 0, 0, 2], 53))?;
+                left.send_to(b"ping", right.local_addr()?).await?;
+                let mut buf = [0; 2];
+                let (len, from) = right.recv_from(&mut buf).await?;
+                assert_eq!((&buf[..len], from), (&b"pi"[..], left.local_addr()?));
+
+                // Nothing is bound to this address, so the datagram goes nowhere.
+                assert_eq!(left.send_to(b"lost", ([10, 0, 0, 3], 53)).await?, 4);
+                Ok(())
+            })
+            .unwrap();
+
+        // The network is only available inside of a simulation.
+        assert!(TcpListener::bind(([10, 0, 0, 1], 80)).is_err());
+        assert!(UdpSocket::bind(([10, 0, 0, 1], 53)).is_err());
+        assert!("nonsense".parse::<Schedule>().is_err());
+    }
+}
@@ -0,0 +1,440 @@
+// MIT/Apache2 License
+
+//! The in-memory network of a simulation.
+
+use super::Simulation;
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+/// The first port handed out to sockets that do not ask for one.
+const EPHEMERAL_PORTS: u16 = 49152;
+
+/// The state of the network.
+#[derive(Default)]
+pub(super) struct Network {
+    /// Listeners, by the address they are bound to.
+    listeners: HashMap<SocketAddr, Arc<Mutex<Backlog>>>,
+
+    /// Datagram sockets, by the address they are bound to.
+    sockets: HashMap<SocketAddr, Arc<Mutex<Inbox>>>,
+
+    /// The number of ephemeral ports handed out so far.
+    ephemeral: u16,
+}
+
+impl Network {
+    /// Pick an unused port for the given address.
+    fn ephemeral(&mut self, mut addr: SocketAddr) -> SocketAddr {
+        loop {
+            addr.set_port(EPHEMERAL_PORTS.wrapping_add(self.ephemeral));
+            self.ephemeral = self.ephemeral.wrapping_add(1) % (u16::MAX - EPHEMERAL_PORTS);
+            if !self.listeners.contains_key(&addr) && !self.sockets.contains_key(&addr) {
+                return addr;
+            }
+        }
+    }
+}
+
+/// Connections waiting to be accepted by a listener.
+#[derive(Default)]
+struct Backlog {
+    /// The connections waiting to be accepted.
+    queue: VecDeque<TcpStream>,
+
+    /// The task waiting to accept a connection.
+    waker: Option<Waker>,
+}
+
+/// Datagrams waiting to be received by a socket.
+#[derive(Default)]
+struct Inbox {
+    /// The datagrams waiting to be received, with the address they came from.
+    queue: VecDeque<(Vec<u8>, SocketAddr)>,
+
+    /// The task waiting to receive a datagram.
+    waker: Option<Waker>,
+}
+
+/// One direction of a connection.
+#[derive(Default)]
+struct Pipe {
+    /// Bytes written but not read yet.
+    buffer: VecDeque<u8>,
+
+    /// Whether the writing side has shut down.
+    closed: bool,
+
+    /// Whether the reading side has been dropped.
+    orphaned: bool,
+
+    /// The task waiting to read from the pipe.
+    reader: Option<Waker>,
+}
+
+/// Get the simulation running on this thread, or fail.
+#[inline]
+fn simulation() -> io::Result<Simulation> {
+    super::current().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the simulated network can only be used inside of a simulation",
+        )
+    })
+}
+
+/// Lock a mutex, ignoring poison.
+#[inline]
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A listener for connections on the simulated network.
+pub struct TcpListener {
+    /// The simulation this listener is part of.
+    sim: Simulation,
+
+    /// The address this listener is bound to.
+    addr: SocketAddr,
+
+    /// Connections waiting to be accepted.
+    backlog: Arc<Mutex<Backlog>>,
+}
+
+impl fmt::Debug for TcpListener {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpListener")
+            .field("addr", &self.addr)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TcpListener {
+    /// Listen for connections on an address in the current simulation.
+    ///
+    /// If the port is zero, an unused port is picked.
+    pub fn bind(addr: impl Into<SocketAddr>) -> io::Result<Self> {
+        let sim = simulation()?;
+        let mut addr = addr.into();
+        let backlog = Arc::new(Mutex::new(Backlog::default()));
+
+        {
+            let mut state = sim.state();
+            let network = &mut state.network;
+            if addr.port() == 0 {
+                addr = network.ephemeral(addr);
+            }
+            if network.listeners.contains_key(&addr) {
+                return Err(io::ErrorKind::AddrInUse.into());
+            }
+            network.listeners.insert(addr, backlog.clone());
+        }
+
+        Ok(Self { sim, addr, backlog })
+    }
+
+    /// Get the address this listener is bound to.
+    #[inline]
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    /// Accept a new connection.
+    pub async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        futures_lite::future::poll_fn(|cx| {
+            let mut backlog = lock(&self.backlog);
+            match backlog.queue.pop_front() {
+                Some(stream) => {
+                    let peer = stream.peer;
+                    Poll::Ready(Ok((stream, peer)))
+                }
+                None => {
+                    backlog.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+}
+
+impl Drop for TcpListener {
+    #[inline]
+    fn drop(&mut self) {
+        self.sim.state().network.listeners.remove(&self.addr);
+    }
+}
+
+/// A connection on the simulated network.
+pub struct TcpStream {
+    /// The simulation this connection is part of.
+    sim: Simulation,
+
+    /// The address of this end of the connection.
+    local: SocketAddr,
+
+    /// The address of the other end of the connection.
+    peer: SocketAddr,
+
+    /// Bytes sent by the other end.
+    incoming: Arc<Mutex<Pipe>>,
+
+    /// Bytes sent by this end.
+    outgoing: Arc<Mutex<Pipe>>,
+}
+
+impl fmt::Debug for TcpStream {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpStream")
+            .field("local", &self.local)
+            .field("peer", &self.peer)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TcpStream {
+    /// Connect to a listener in the current simulation.
+    pub async fn connect(addr: impl Into<SocketAddr>) -> io::Result<Self> {
+        let sim = simulation()?;
+        let peer = addr.into();
+
+        let (backlog, local) = {
+            let mut state = sim.state();
+            let network = &mut state.network;
+            let backlog = network
+                .listeners
+                .get(&peer)
+                .cloned()
+                .ok_or(io::ErrorKind::ConnectionRefused)?;
+            (backlog, network.ephemeral(peer))
+        };
+
+        let to_peer = Arc::new(Mutex::new(Pipe::default()));
+        let from_peer = Arc::new(Mutex::new(Pipe::default()));
+        let server = TcpStream {
+            sim: sim.clone(),
+            local: peer,
+            peer: local,
+            incoming: to_peer.clone(),
+            outgoing: from_peer.clone(),
+        };
+
+        // Hand the other end to the listener.
+        let waker = {
+            let mut backlog = lock(&backlog);
+            backlog.queue.push_back(server);
+            backlog.waker.take()
+        };
+        if let Some(waker) = waker {
+            sim.defer(waker);
+        }
+
+        Ok(TcpStream {
+            sim,
+            local,
+            peer,
+            incoming: from_peer,
+            outgoing: to_peer,
+        })
+    }
+
+    /// Get the address of this end of the connection.
+    #[inline]
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local)
+    }
+
+    /// Get the address of the other end of the connection.
+    #[inline]
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.peer)
+    }
+
+    /// Wake the other end up after changing the outgoing pipe.
+    #[inline]
+    fn notify(&self, mut pipe: MutexGuard<'_, Pipe>) {
+        let waker = pipe.reader.take();
+        drop(pipe);
+        if let Some(waker) = waker {
+            self.sim.defer(waker);
+        }
+    }
+}
+
+impl AsyncRead for TcpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = lock(&self.incoming);
+        if !pipe.buffer.is_empty() {
+            let len = buf.len().min(pipe.buffer.len());
+            for (slot, byte) in buf.iter_mut().zip(pipe.buffer.drain(..len)) {
+                *slot = byte;
+            }
+            return Poll::Ready(Ok(len));
+        }
+
+        if pipe.closed {
+            return Poll::Ready(Ok(0));
+        }
+
+        pipe.reader = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl AsyncWrite for TcpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = lock(&self.outgoing);
+        if pipe.closed || pipe.orphaned {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+
+        // The simulated network has no limit on buffered data.
+        pipe.buffer.extend(buf);
+        self.notify(pipe);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut pipe = lock(&self.outgoing);
+        pipe.closed = true;
+        self.notify(pipe);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for TcpStream {
+    #[inline]
+    fn drop(&mut self) {
+        lock(&self.incoming).orphaned = true;
+
+        let mut pipe = lock(&self.outgoing);
+        pipe.closed = true;
+        self.notify(pipe);
+    }
+}
+
+/// A datagram socket on the simulated network.
+///
+/// Datagrams are never lost or reordered between two sockets, but datagrams sent to an
+/// address that nothing is bound to are dropped.
+pub struct UdpSocket {
+    /// The simulation this socket is part of.
+    sim: Simulation,
+
+    /// The address this socket is bound to.
+    addr: SocketAddr,
+
+    /// Datagrams waiting to be received.
+    inbox: Arc<Mutex<Inbox>>,
+}
+
+impl fmt::Debug for UdpSocket {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UdpSocket")
+            .field("addr", &self.addr)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UdpSocket {
+    /// Bind a socket to an address in the current simulation.
+    ///
+    /// If the port is zero, an unused port is picked.
+    pub fn bind(addr: impl Into<SocketAddr>) -> io::Result<Self> {
+        let sim = simulation()?;
+        let mut addr = addr.into();
+        let inbox = Arc::new(Mutex::new(Inbox::default()));
+
+        {
+            let mut state = sim.state();
+            let network = &mut state.network;
+            if addr.port() == 0 {
+                addr = network.ephemeral(addr);
+            }
+            if network.sockets.contains_key(&addr) {
+                return Err(io::ErrorKind::AddrInUse.into());
+            }
+            network.sockets.insert(addr, inbox.clone());
+        }
+
+        Ok(Self { sim, addr, inbox })
+    }
+
+    /// Get the address this socket is bound to.
+    #[inline]
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    /// Send a datagram to the given address.
+    pub async fn send_to(&self, buf: &[u8], addr: impl Into<SocketAddr>) -> io::Result<usize> {
+        let addr = addr.into();
+        let inbox = self.sim.state().network.sockets.get(&addr).cloned();
+
+        // Like a real network, nobody finds out about datagrams that go nowhere.
+        if let Some(inbox) = inbox {
+            let waker = {
+                let mut inbox = lock(&inbox);
+                inbox.queue.push_back((buf.to_vec(), self.addr));
+                inbox.waker.take()
+            };
+            if let Some(waker) = waker {
+                self.sim.defer(waker);
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    /// Receive a datagram, along with the address it came from.
+    ///
+    /// If the datagram does not fit into `buf`, the rest of it is discarded.
+    pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        futures_lite::future::poll_fn(|cx| {
+            let mut inbox = lock(&self.inbox);
+            match inbox.queue.pop_front() {
+                Some((datagram, addr)) => {
+                    let len = buf.len().min(datagram.len());
+                    buf[..len].copy_from_slice(&datagram[..len]);
+                    Poll::Ready(Ok((len, addr)))
+                }
+                None => {
+                    inbox.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+}
+
+impl Drop for UdpSocket {
+    #[inline]
+    fn drop(&mut self) {
+        self.sim.state().network.sockets.remove(&self.addr);
+    }
+}
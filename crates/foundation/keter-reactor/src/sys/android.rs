@@ -2,13 +2,12 @@
 
 //! Code for the Android platform.
 
-#[path = "signal.rs"]
-mod signal;
+use crate::builder::Config;
+use crate::exit::Signal;
 
 use android_activity::{AndroidApp, AndroidAppWaker, PollEvent};
 use futures_lite::prelude::*;
 use once_cell::sync::OnceCell;
-use signal::Signal;
 
 use std::future::Future;
 use std::io;
@@ -17,6 +16,8 @@ use std::sync::Arc;
 use std::task::{Context, Poll, Wake, Waker};
 use std::time::Duration;
 
+use crate::timer_queue::{QueuedTimer, TimerQueue};
+
 /// Settings for the Android event loop.
 pub(crate) struct Settings {
     /// The android application to run.
@@ -31,13 +32,33 @@ impl Settings {
     }
 }
 
+/// Platform-specific options set through the builder.
+///
+/// There are currently no Android-specific options.
+#[derive(Debug, Default)]
+pub(crate) struct Options {}
+
 /// Run the reactor.
-pub(crate) fn block_on<T>(settings: Settings, f: impl Future<Output = T>) -> io::Result<Option<T>> {
+///
+/// Returns `Err` with the exit code if the exit signal was received before the future
+/// completed.
+pub(crate) fn block_on<T>(
+    settings: &Settings,
+    config: &Config,
+    exit: &Signal,
+    f: impl Future<Output = T>,
+) -> io::Result<Result<T, i32>> {
     let reactor = Reactor::get();
+    let app = settings.app.clone();
 
     // We are now running the reactor make sure to set it to "not running" on our way out.
-    let old_state = reactor.state.swap(NOTIFIED, Ordering::SeqCst);
-    assert_eq!(old_state, NOT_RUNNING);
+    if reactor
+        .state
+        .compare_exchange(NOT_RUNNING, NOTIFIED, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err(io::Error::other("another reactor is already running"));
+    }
     let _guard = CallOnDrop(|| {
         reactor.state.store(NOT_RUNNING, Ordering::Release);
     });
@@ -45,16 +66,18 @@ pub(crate) fn block_on<T>(settings: Settings, f: impl Future<Output = T>) -> io:
     // Create the future to poll.
     let future = async move {
         // Poll the future given by the user.
-        let user_future = async move { Some(f.await) };
+        let user_future = async move { Ok(f.await) };
 
         // Simultaneously, wait for the exit signal to be fired.
-        let wait_for_end = async {
-            Signal::get().wait().await;
-            None
-        };
+        let wait_for_end = async { Err(exit.wait().await) };
 
         // Run these futures in parallel.
-        user_future.or(wait_for_end).await
+        let result = user_future.or(wait_for_end).await;
+
+        // Give the exit hooks a chance to run, alongside any spawned tasks.
+        crate::executor::run(crate::shutdown::run(config.exit_deadline)).await;
+
+        result
     };
 
     // Pin the future to the stack.
@@ -63,7 +86,7 @@ pub(crate) fn block_on<T>(settings: Settings, f: impl Future<Output = T>) -> io:
     // Create a waker to poll the future with.
     let waker = Waker::from(Arc::new(ReactorWaker {
         reactor,
-        waker: settings.app.create_waker(),
+        waker: app.create_waker(),
     }));
     let mut context = Context::from_waker(&waker);
 
@@ -74,8 +97,11 @@ pub(crate) fn block_on<T>(settings: Settings, f: impl Future<Output = T>) -> io:
         let timeout = if was_notified {
             Some(Duration::from_secs(0))
         } else {
-            // TODO: Handle timers.
-            None
+            // Sleep until the next timer fires.
+            reactor
+                .timers
+                .next_deadline()
+                .map(|when| when.saturating_duration_since(crate::Instant::now()))
         };
 
         // If we are about to go to sleep, indicate that we will be asleep.
@@ -88,7 +114,7 @@ pub(crate) fn block_on<T>(settings: Settings, f: impl Future<Output = T>) -> io:
         }
 
         // Go to sleep and poll for events.
-        settings.app.poll_events(timeout, |event| {
+        app.poll_events(timeout, |event| {
             // If we were previously asleep, we are now awake.
             let _ =
                 reactor
@@ -98,6 +124,11 @@ pub(crate) fn block_on<T>(settings: Settings, f: impl Future<Output = T>) -> io:
             // Handle the event.
             reactor.handle_event(event);
 
+            // Wake up any timers that have fired.
+            for waker in reactor.timers.take_due(crate::Instant::now()) {
+                waker.wake();
+            }
+
             // Poll the future if it is notified.
             if let NOTIFIED = reactor.state.swap(AWAKE, Ordering::SeqCst) {
                 // If the future is ready, we're done.
@@ -118,14 +149,14 @@ pub(crate) fn block_on<T>(settings: Settings, f: impl Future<Output = T>) -> io:
     }
 }
 
-/// Send the signal to exit.
-pub(crate) fn exit() -> io::Result<()> {
-    Signal::get().stop();
-    Ok(())
+/// Get the deadline of the next timer, if it is known.
+#[inline]
+pub(crate) fn next_deadline() -> Option<crate::Instant> {
+    Reactor::get().timers.next_deadline()
 }
 
 /// The timer implementation.
-pub(crate) struct Timer(());
+pub(crate) struct Timer(QueuedTimer<&'static TimerQueue>);
 
 impl Unpin for Timer {}
 
@@ -133,43 +164,43 @@ impl Timer {
     /// Create a timer that will never fire.
     #[inline]
     pub(crate) fn never() -> Self {
-        todo!()
-    }
-
-    /// Create a timer that fires at a specific deadline.
-    #[inline]
-    pub(crate) fn at(at: crate::Instant) -> Self {
-        todo!()
-    }
-
-    /// Create a timer that fires repeatedly.
-    #[inline]
-    pub(crate) fn interval(at: crate::Instant, interval: crate::Duration) -> Self {
-        todo!()
+        Self(QueuedTimer::new(&Reactor::get().timers))
     }
 
     /// Set this timer to never fire.
     #[inline]
     pub(crate) fn set_never(&mut self) {
-        todo!()
+        self.0.set_never();
     }
 
     /// Set this timer to an `at()` timer.
     #[inline]
     pub(crate) fn set_at(&mut self, at: crate::Instant) {
-        todo!()
+        self.set_interval(at, Duration::MAX);
     }
 
     /// Set this timer to an `interval()` timer.
     #[inline]
     pub(crate) fn set_interval(&mut self, at: crate::Instant, interval: crate::Duration) {
-        todo!()
+        self.0.set_interval(at, interval);
+    }
+
+    /// Get the next time this timer fires.
+    #[inline]
+    pub(crate) fn deadline(&self) -> Option<crate::Instant> {
+        self.0.deadline()
+    }
+
+    /// Get the period of this timer, if it fires repeatedly.
+    #[inline]
+    pub(crate) fn period(&self) -> Option<crate::Duration> {
+        self.0.period()
     }
 
     /// Wait for the next time this timer fires.
     #[inline]
     pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
-        todo!()
+        self.0.poll(crate::Instant::now(), cx)
     }
 }
 
@@ -194,6 +225,9 @@ const AWAKE: usize = 3;
 struct Reactor {
     /// The current state of the reactor.
     state: AtomicUsize,
+
+    /// Timers registered with the reactor.
+    timers: TimerQueue,
 }
 
 impl Reactor {
@@ -204,6 +238,7 @@ impl Reactor {
 
         REACTOR.get_or_init(|| Reactor {
             state: AtomicUsize::new(NOT_RUNNING),
+            timers: TimerQueue::new(),
         })
     }
 
@@ -1,50 +1,166 @@
 // MIT/Apache2 License
 
 //! Implementation for free-Unix systems.
+//!
+//! The reactor is driven by `async-io`, which uses epoll on Linux. With the `io-uring`
+//! feature, reactors on Linux can run on io_uring instead; see the `uring` module.
 
-#[path = "signal.rs"]
-mod signal;
+use super::wheel;
+use crate::builder::Config;
+use crate::exit::Signal;
 
 use futures_lite::prelude::*;
-use signal::Signal;
 
 use std::future::Future;
 use std::io;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 /// Run the reactor.
-pub(crate) fn block_on<T>(settings: Settings, f: impl Future<Output = T>) -> io::Result<Option<T>> {
+///
+/// Returns `Err` with the exit code if the exit signal was received before the future
+/// completed.
+pub(crate) fn block_on<T>(
+    settings: &Settings,
+    config: &Config,
+    exit: &Signal,
+    f: impl Future<Output = T>,
+) -> io::Result<Result<T, i32>> {
+    // Run on io_uring if it was asked for, and fall back to epoll if it is not available.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    if settings.io_uring {
+        if let Some(driver) = super::uring::ring() {
+            let reactor = reactor(settings, config, exit, f)?;
+            return super::uring::block_on(&driver, reactor)?;
+        }
+    }
+
+    // Use async_io to block on the reactor.
+    let reactor = run(settings, config, exit, f)?;
+    async_io::block_on(reactor)
+}
+
+/// Get a future that runs the reactor, without blocking on it.
+///
+/// The future resolves to `Err` with the exit code if the exit signal was received before
+/// the future completed, or to an error if the reactor failed.
+pub(crate) fn run<'a, T: 'a>(
+    settings: &Settings,
+    config: &'a Config,
+    exit: &'a Signal,
+    f: impl Future<Output = T> + 'a,
+) -> io::Result<impl Future<Output = io::Result<Result<T, i32>>> + 'a> {
+    let reactor = reactor(settings, config, exit, f)?;
+
+    Ok(async {
+        // Sources registered with io_uring cannot use it while this reactor runs.
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        let _scope = super::uring::Scope::enter(false);
+
+        // Fire the timers created on this thread while the reactor runs. This is polled
+        // first so that timers created during the first poll already use the wheel.
+        wheel::drive().await;
+        unreachable!("the timer wheel is driven forever")
+    }
+    .or(reactor))
+}
+
+/// Get a future that runs the user's future, the exit signal and the exit hooks.
+///
+/// This does not drive the timer wheel.
+fn reactor<'a, T: 'a>(
+    settings: &Settings,
+    config: &'a Config,
+    exit: &'a Signal,
+    f: impl Future<Output = T> + 'a,
+) -> io::Result<impl Future<Output = io::Result<Result<T, i32>>> + 'a> {
     if !settings.any_thread {
         crate::check_main_thread()?;
     }
 
-    // Use async_io to block on this future.
-    let result = async_io::block_on(async move {
+    // If we exit on signals, start listening for them now.
+    let exit_signals = if settings.exit_on_signal {
+        Some(async_signal::Signals::new([
+            async_signal::Signal::Int,
+            async_signal::Signal::Term,
+        ])?)
+    } else {
+        None
+    };
+
+    let reactor = async move {
         // Poll the future given by the user.
-        let user_future = async move { Some(f.await) };
+        let user_future = async move { Ok(Ok(f.await)) };
 
         // Simultaneously, wait for the exit signal to be fired.
-        let wait_for_end = async {
-            Signal::get().wait().await;
-            None
+        let wait_for_end = async { Ok(Err(exit.wait().await)) };
+
+        // If we exit on signals, treat them the same as the exit signal.
+        let wait_for_signal = async {
+            let Some(mut signals) = exit_signals else {
+                return std::future::pending().await;
+            };
+
+            match signals.next().await {
+                Some(Ok(_)) => {
+                    // Signals are treated as a clean shutdown.
+                    exit.stop(0);
+                    std::future::pending().await
+                }
+                Some(Err(err)) => Err(err),
+                None => Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the signal stream closed",
+                )),
+            }
         };
 
         // Run these futures in parallel.
-        user_future.or(wait_for_end).await
-    });
+        let result = user_future.or(wait_for_end).or(wait_for_signal).await;
+
+        // Give the exit hooks a chance to run, alongside any spawned tasks.
+        crate::executor::run(crate::shutdown::run(config.exit_deadline)).await;
 
-    Ok(result)
+        result
+    };
+
+    Ok(reactor)
 }
 
-/// Send the signal to exit.
-pub(crate) fn exit() -> io::Result<()> {
-    Signal::get().stop();
-    Ok(())
+/// Get the deadline of the next timer, if it is known.
+#[inline]
+pub(crate) fn next_deadline() -> Option<crate::Instant> {
+    // Timers created outside of a reactor use async-io, which does not expose its timers.
+    wheel::current().and_then(|queue| queue.next_deadline())
 }
 
 /// The timer implementation.
-pub(crate) struct Timer(async_io::Timer);
+pub(crate) struct Timer {
+    /// The underlying timer.
+    inner: TimerInner,
+
+    /// The next time this timer fires.
+    when: Option<crate::Instant>,
+
+    /// The period of the timer, or `Duration::MAX` if it only fires once.
+    period: crate::Duration,
+}
+
+/// Where a [`Timer`] is registered.
+enum TimerInner {
+    /// In the timer wheel of the reactor running on the thread that last polled the timer.
+    Wheel {
+        /// The wheel.
+        queue: Arc<wheel::Queue>,
+
+        /// The timer's registration in the wheel.
+        key: Option<wheel::Key>,
+    },
+
+    /// With async-io, if the timer was last polled outside of a reactor.
+    Io(async_io::Timer),
+}
 
 impl Unpin for Timer {}
 
@@ -52,59 +168,173 @@ impl Timer {
     /// Create a timer that will never fire.
     #[inline]
     pub(crate) fn never() -> Self {
-        Self(async_io::Timer::never())
-    }
+        let inner = match wheel::current() {
+            Some(queue) => TimerInner::Wheel { queue, key: None },
+            None => TimerInner::Io(async_io::Timer::never()),
+        };
 
-    /// Create a timer that fires at a specific deadline.
-    #[inline]
-    pub(crate) fn at(at: crate::Instant) -> Self {
-        Self(async_io::Timer::at(at))
-    }
-
-    /// Create a timer that fires repeatedly.
-    #[inline]
-    pub(crate) fn interval(at: crate::Instant, interval: crate::Duration) -> Self {
-        Self(async_io::Timer::interval_at(at, interval))
+        Self {
+            inner,
+            when: None,
+            period: crate::Duration::MAX,
+        }
     }
 
     /// Set this timer to never fire.
     #[inline]
     pub(crate) fn set_never(&mut self) {
-        self.0 = async_io::Timer::never();
+        match &mut self.inner {
+            TimerInner::Wheel { queue, key } => {
+                if let Some(key) = key.take() {
+                    queue.remove(key);
+                }
+            }
+            TimerInner::Io(timer) => *timer = async_io::Timer::never(),
+        }
+        self.when = None;
     }
 
     /// Set this timer to an `at()` timer.
     #[inline]
     pub(crate) fn set_at(&mut self, at: crate::Instant) {
-        self.0.set_at(at);
+        self.set_interval(at, crate::Duration::MAX);
     }
 
     /// Set this timer to an `interval()` timer.
     #[inline]
     pub(crate) fn set_interval(&mut self, at: crate::Instant, interval: crate::Duration) {
-        self.0.set_interval_at(at, interval);
+        match &mut self.inner {
+            TimerInner::Wheel { queue, key } => {
+                // Re-register the timer with its new deadline.
+                if let Some(waker) = key.take().and_then(|key| queue.remove(key)) {
+                    *key = Some(queue.insert(at, &waker));
+                }
+            }
+            TimerInner::Io(timer) if interval == crate::Duration::MAX => timer.set_at(at),
+            TimerInner::Io(timer) => timer.set_interval_at(at, interval),
+        }
+        self.when = Some(at);
+        self.period = interval;
+    }
+
+    /// Get the next time this timer fires.
+    #[inline]
+    pub(crate) fn deadline(&self) -> Option<crate::Instant> {
+        self.when
+    }
+
+    /// Get the period of this timer, if it fires repeatedly.
+    #[inline]
+    pub(crate) fn period(&self) -> Option<crate::Duration> {
+        (self.period != crate::Duration::MAX).then_some(self.period)
     }
 
     /// Wait for the next time this timer fires.
     #[inline]
     pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
-        futures_lite::ready!(Pin::new(&mut self.0).poll_next(cx));
-        Poll::Ready(())
+        self.rehome();
+
+        let (queue, key) = match &mut self.inner {
+            TimerInner::Wheel { queue, key } => (queue, key),
+            TimerInner::Io(timer) => {
+                futures_lite::ready!(Pin::new(timer).poll_next(cx));
+
+                // async-io schedules the next tick one period after the last one.
+                self.when = self.when.and_then(|when| when.checked_add(self.period));
+                return Poll::Ready(());
+            }
+        };
+
+        let Some(when) = self.when else {
+            return Poll::Pending;
+        };
+
+        if crate::Instant::now() >= when {
+            // The timer has fired; remove its registration.
+            if let Some(key) = key.take() {
+                queue.remove(key);
+            }
+
+            // Schedule the next tick, if there is one.
+            self.when = when.checked_add(self.period);
+            if let Some(next) = self.when {
+                *key = Some(queue.insert(next, cx.waker()));
+            }
+
+            return Poll::Ready(());
+        }
+
+        // Register the timer, or update its waker if it is still registered.
+        match key {
+            Some(registered) if queue.update_waker(*registered, cx.waker()) => {}
+            _ => *key = Some(queue.insert(when, cx.waker())),
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Timer {
+    /// Move the timer to the wheel driven on this thread, or to async-io if there is none.
+    ///
+    /// A wheel only fires timers while its reactor runs, so timers that are polled after
+    /// the reactor stops or on another thread have to move.
+    fn rehome(&mut self) {
+        let current = wheel::current();
+        match (&self.inner, &current) {
+            (TimerInner::Wheel { queue, .. }, Some(current)) if Arc::ptr_eq(queue, current) => {
+                return
+            }
+            (TimerInner::Io(_), None) => return,
+            _ => {}
+        }
+
+        if let TimerInner::Wheel {
+            queue,
+            key: Some(key),
+        } = &self.inner
+        {
+            queue.remove(*key);
+        }
+
+        self.inner = match current {
+            Some(queue) => TimerInner::Wheel { queue, key: None },
+            None => TimerInner::Io(async_io::Timer::never()),
+        };
+        if let Some(when) = self.when {
+            self.set_interval(when, self.period);
+        }
+    }
+}
+
+impl Drop for Timer {
+    #[inline]
+    fn drop(&mut self) {
+        if let TimerInner::Wheel {
+            queue,
+            key: Some(key),
+        } = &self.inner
+        {
+            queue.remove(*key);
+        }
     }
 }
 
+/// Platform-specific options set through the builder.
+pub(crate) type Options = Settings;
+
 /// Settings for running the reactor.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub(crate) struct Settings {
     /// Run on any thread.
     pub(crate) any_thread: bool,
-}
 
-impl Settings {
-    #[inline]
-    pub(crate) fn empty() -> Self {
-        Self { any_thread: false }
-    }
+    /// Exit when `SIGINT` or `SIGTERM` is received.
+    pub(crate) exit_on_signal: bool,
+
+    /// Run on io_uring instead of epoll, if it is available.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub(crate) io_uring: bool,
 }
 
 #[cfg(target_os = "linux")]
@@ -121,3 +351,87 @@ pub(crate) fn is_main_thread() -> bool {
 pub(crate) fn is_main_thread() -> bool {
     std::thread::current().name() == Some("main")
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_reactor, Timer};
+
+    use futures_lite::future;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn timer_outlives_reactor() {
+        // Create a timer in a running reactor, after its first poll.
+        fn armed_timer(delay: Duration) -> Timer {
+            let reactor = test_reactor(|builder| builder);
+            let mut timer = None;
+            reactor
+                .__block_on_result(async {
+                    future::yield_now().await;
+                    let mut armed = Timer::after(delay);
+                    assert!(future::poll_once(&mut armed).await.is_none());
+                    timer = Some(armed);
+                    Ok(())
+                })
+                .unwrap();
+            timer.unwrap()
+        }
+
+        // The timer still fires after its reactor stops.
+        let start = Instant::now();
+        let timer = armed_timer(Duration::from_millis(300));
+        let fired = future::block_on(future::or(
+            async {
+                timer.await;
+                true
+            },
+            async {
+                async_io::Timer::after(Duration::from_secs(5)).await;
+                false
+            },
+        ));
+        assert!(fired);
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // The timer also fires in a reactor on another thread.
+        let start = Instant::now();
+        let timer = armed_timer(Duration::from_millis(300));
+        std::thread::spawn(move || {
+            let reactor = test_reactor(|builder| builder);
+            reactor
+                .__block_on_result(async {
+                    let fired = future::or(
+                        async {
+                            timer.await;
+                            true
+                        },
+                        async {
+                            async_io::Timer::after(Duration::from_secs(5)).await;
+                            false
+                        },
+                    );
+                    assert!(fired.await);
+                    Ok(())
+                })
+                .unwrap();
+        })
+        .join()
+        .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn timer_first_poll() {
+        let reactor = test_reactor(|builder| builder);
+        reactor
+            .__block_on_result(async {
+                // Timers polled during the first poll are driven by the reactor.
+                let mut timer = Timer::after(Duration::from_secs(60));
+                assert!(future::poll_once(&mut timer).await.is_none());
+                let deadline = crate::time::next_deadline().unwrap();
+                assert!(deadline <= timer.deadline().unwrap());
+                Ok(())
+            })
+            .unwrap();
+    }
+}
@@ -8,5 +8,14 @@
 )]
 #[cfg_attr(target_os = "android", path = "android.rs")]
 mod inner;
+#[cfg(target_os = "linux")]
+mod timerfd;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub(crate) mod uring;
+#[cfg(all(unix, not(target_vendor = "apple"), not(target_os = "android")))]
+pub(crate) mod wheel;
+
+#[cfg(target_os = "linux")]
+pub(crate) use timerfd::ClockTimer;
 
 pub(crate) use inner::*;
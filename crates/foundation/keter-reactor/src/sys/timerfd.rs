@@ -0,0 +1,271 @@
+// MIT/Apache2 License
+
+//! Timers on clocks other than the monotonic clock, using `timerfd`.
+
+use crate::platform::poll_io::Async;
+use crate::TimerClock;
+
+use rustix::io::Errno;
+use rustix::time::{
+    timerfd_create, timerfd_settime, ClockId, Itimerspec, TimerfdClockId, TimerfdFlags,
+    TimerfdTimerFlags, Timespec,
+};
+
+use std::io;
+use std::os::unix::io::OwnedFd;
+use std::task::{Context, Poll};
+
+use web_time::{Duration, SystemTime};
+
+/// A timer running against the wall clock or the boot clock.
+pub(crate) struct ClockTimer {
+    /// The `timerfd` registered with the reactor.
+    source: Async<OwnedFd>,
+
+    /// The clock the timer runs against.
+    clock: TimerClock,
+
+    /// The next time the timer fires, measured from the start of the clock.
+    target: Option<Duration>,
+
+    /// The period of the timer, or `Duration::MAX` if it only fires once.
+    period: Duration,
+}
+
+impl ClockTimer {
+    /// Create a timer on the given clock that never fires.
+    pub(crate) fn new(clock: TimerClock) -> io::Result<Self> {
+        let id = match clock {
+            TimerClock::Wall => TimerfdClockId::Realtime,
+            TimerClock::Boot => TimerfdClockId::Boottime,
+            TimerClock::Monotonic => TimerfdClockId::Monotonic,
+        };
+        let fd = timerfd_create(id, TimerfdFlags::NONBLOCK | TimerfdFlags::CLOEXEC)?;
+
+        Ok(Self {
+            source: Async::with_nonblocking(fd)?,
+            clock,
+            target: None,
+            period: Duration::MAX,
+        })
+    }
+
+    /// Get the clock this timer runs against.
+    #[inline]
+    pub(crate) fn clock(&self) -> TimerClock {
+        self.clock
+    }
+
+    /// Set this timer to never fire.
+    #[inline]
+    pub(crate) fn set_never(&mut self) {
+        self.target = None;
+
+        // Disarming a valid timer cannot fail.
+        let _ = self.arm();
+    }
+
+    /// Set this timer to fire after `after`, and then every `period`.
+    #[inline]
+    pub(crate) fn set_after(&mut self, after: Duration, period: Duration) -> io::Result<()> {
+        self.target = Some(now(self.clock).saturating_add(after));
+        self.period = period;
+        self.arm()
+    }
+
+    /// Set this timer to fire at `time`, and then every `period`.
+    #[inline]
+    pub(crate) fn set_system_time(&mut self, time: SystemTime, period: Duration) -> io::Result<()> {
+        match self.clock {
+            TimerClock::Wall => {
+                // Times before the epoch have already passed.
+                self.target = Some(
+                    time.duration_since(SystemTime::UNIX_EPOCH)
+                        .unwrap_or_default(),
+                );
+                self.period = period;
+                self.arm()
+            }
+            _ => {
+                let after = time.duration_since(SystemTime::now()).unwrap_or_default();
+                self.set_after(after, period)
+            }
+        }
+    }
+
+    /// Get how long it is until the timer fires next.
+    #[inline]
+    pub(crate) fn remaining(&self) -> Option<Duration> {
+        self.target
+            .map(|target| target.saturating_sub(now(self.clock)))
+    }
+
+    /// Get the period of this timer, if it fires repeatedly.
+    #[inline]
+    pub(crate) fn period(&self) -> Option<Duration> {
+        (self.period != Duration::MAX).then_some(self.period)
+    }
+
+    /// Wait for the next time this timer fires.
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            let Some(target) = self.target else {
+                return Poll::Pending;
+            };
+
+            let mut expirations = [0u8; 8];
+            match rustix::io::read(self.source.get_ref(), &mut expirations) {
+                Ok(_) => {
+                    // Skip the ticks that were missed.
+                    let missed = u64::from_ne_bytes(expirations).saturating_sub(1);
+                    self.target = self.period().and_then(|period| {
+                        let missed = u32::try_from(missed).ok()?;
+                        target.checked_add(period.checked_mul(missed)?.checked_add(period)?)
+                    });
+                    return Poll::Ready(());
+                }
+
+                // The wall clock was changed, so the timer has to be armed again.
+                Err(Errno::CANCELED) => {
+                    if self.arm().is_err() {
+                        return self.fail();
+                    }
+                }
+
+                Err(Errno::INTR) => {}
+
+                Err(Errno::AGAIN) => match self.source.poll_readable(cx) {
+                    Poll::Ready(Ok(())) => {}
+                    Poll::Ready(Err(_)) => return self.fail(),
+                    Poll::Pending => return Poll::Pending,
+                },
+
+                Err(_) => return self.fail(),
+            }
+        }
+    }
+
+    /// Give up on a timer that can no longer be waited on.
+    ///
+    /// Firing early is preferred over never firing at all.
+    #[inline]
+    fn fail(&mut self) -> Poll<()> {
+        self.target = None;
+        Poll::Ready(())
+    }
+
+    /// Point the `timerfd` at the current target.
+    fn arm(&mut self) -> io::Result<()> {
+        let mut flags = TimerfdTimerFlags::ABSTIME;
+        if self.clock == TimerClock::Wall {
+            // Find out when the wall clock is changed, so the timer can be armed again.
+            flags |= TimerfdTimerFlags::CANCEL_ON_SET;
+        }
+
+        // A zero value disarms the timer, so targets at the start of the clock are moved
+        // forward by a nanosecond.
+        let value = match self.target {
+            Some(target) => timespec(target.max(Duration::from_nanos(1))),
+            None => timespec(Duration::ZERO),
+        };
+        let interval = match self.period() {
+            Some(period) if self.target.is_some() => timespec(period),
+            _ => timespec(Duration::ZERO),
+        };
+
+        timerfd_settime(
+            self.source.get_ref(),
+            flags,
+            &Itimerspec {
+                it_interval: interval,
+                it_value: value,
+            },
+        )?;
+        Ok(())
+    }
+}
+
+/// Get the current time on a clock, measured from the start of the clock.
+#[inline]
+fn now(clock: TimerClock) -> Duration {
+    let id = match clock {
+        TimerClock::Wall => ClockId::Realtime,
+        TimerClock::Boot => ClockId::Boottime,
+        TimerClock::Monotonic => ClockId::Monotonic,
+    };
+    let time = rustix::time::clock_gettime(id);
+    Duration::new(
+        u64::try_from(time.tv_sec).unwrap_or(0),
+        u32::try_from(time.tv_nsec).unwrap_or(0),
+    )
+}
+
+/// Convert a duration into a `timespec`.
+#[inline]
+fn timespec(duration: Duration) -> Timespec {
+    Timespec {
+        tv_sec: i64::try_from(duration.as_secs()).unwrap_or(i64::MAX),
+        tv_nsec: i64::from(duration.subsec_nanos()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::VirtualClock;
+    use crate::{test_reactor, Timer};
+
+    use futures_lite::{future, StreamExt};
+    use std::time::Instant;
+
+    #[test]
+    fn timer_clocks() {
+        let ms = Duration::from_millis;
+        let reactor = test_reactor(|builder| builder);
+        reactor
+            .__block_on_result(async move {
+                // Wall clock timers fire once the system time is reached.
+                let start = Instant::now();
+                let mut timer = Timer::at_system_time(SystemTime::now() + ms(20));
+                assert_eq!(timer.clock(), TimerClock::Wall);
+                assert!(timer.deadline().unwrap() <= start + ms(25));
+                (&mut timer).await;
+                assert!(start.elapsed() >= ms(15));
+                assert!(!timer.is_armed());
+
+                // Times that have already passed fire right away.
+                timer.set_at_system_time(SystemTime::UNIX_EPOCH);
+                assert!(future::poll_once(&mut timer).await.is_some());
+
+                // Boot clock timers support the usual methods.
+                let start = Instant::now();
+                let mut timer = Timer::never_on(TimerClock::Boot);
+                assert_eq!(timer.clock(), TimerClock::Boot);
+                assert!(!timer.is_armed());
+                timer.set_interval(ms(5));
+                assert_eq!(timer.period(), Some(ms(5)));
+                timer.next().await;
+                timer.next().await;
+                assert!(start.elapsed() >= ms(10));
+                assert!(timer.is_armed());
+
+                timer.set_never();
+                assert!(future::poll_once(&mut timer).await.is_none());
+                Ok(())
+            })
+            .unwrap();
+
+        // Virtual clocks only have a monotonic clock.
+        let reactor = test_reactor(|builder| {
+            builder.virtual_clock(VirtualClock::new().with_auto_advance(true))
+        });
+        reactor
+            .__block_on_result(async move {
+                let timer = Timer::at_system_time(SystemTime::now() + Duration::from_secs(60));
+                assert_eq!(timer.clock(), TimerClock::Monotonic);
+                timer.await;
+                Ok(())
+            })
+            .unwrap();
+    }
+}
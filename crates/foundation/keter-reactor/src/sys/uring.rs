@@ -0,0 +1,540 @@
+// MIT/Apache2 License
+
+//! Running the reactor on io_uring.
+//!
+//! Instead of handing the reactor to `async-io`, which waits with epoll, this runs its
+//! own loop around an io_uring from `keter-uring`. Each iteration polls the future, fires
+//! the timers in the wheel and then sleeps in a single `io_uring_enter` call. That call
+//! submits the polls queued by [`poll_io::Async`] sources since the last iteration, and
+//! waits until one of them completes, the loop is woken or the next timer is due.
+//!
+//! Every reactor on a thread shares one ring. Sources only use the ring while the
+//! innermost reactor on the thread polling them drives it. Anywhere else, they fall back
+//! to `async-io`, the same way timers move out of the wheel.
+//!
+//! [`poll_io::Async`]: crate::platform::poll_io::Async
+
+use super::wheel;
+
+use keter_uring::{Completion, Ring};
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::os::unix::io::{AsFd, BorrowedFd, OwnedFd};
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Wake, Waker};
+
+use web_time::{Duration, Instant};
+
+pub(crate) use keter_uring::Interest;
+
+/// The number of operations the ring can queue before they are handed to the kernel.
+const ENTRIES: u32 = 256;
+
+thread_local! {
+    /// The ring for this thread, or `None` if io_uring is not available.
+    static RING: OnceCell<Option<Arc<Driver>>> = const { OnceCell::new() };
+
+    /// Whether each reactor running on this thread drives the ring, innermost last.
+    static DRIVING: RefCell<Vec<bool>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Get the ring for this thread, creating it the first time.
+///
+/// Returns `None` if io_uring is not available.
+pub(crate) fn ring() -> Option<Arc<Driver>> {
+    RING.with(|ring| {
+        ring.get_or_init(|| {
+            Ring::new(ENTRIES)
+                .ok()
+                .map(|ring| Arc::new(Driver::new(ring)))
+        })
+        .clone()
+    })
+}
+
+/// Get the ring for this thread, if the innermost reactor running on it drives the ring.
+#[inline]
+pub(crate) fn current() -> Option<Arc<Driver>> {
+    if DRIVING.with(|driving| driving.borrow().last() != Some(&true)) {
+        return None;
+    }
+
+    RING.with(|ring| ring.get().cloned().flatten())
+}
+
+/// Run the reactor on the ring until the future completes.
+pub(crate) fn block_on<T>(driver: &Arc<Driver>, future: impl Future<Output = T>) -> io::Result<T> {
+    let _scope = Scope::enter(true);
+    let timers = wheel::Driver::enter();
+
+    let unparker = Arc::new(Unparker {
+        driver: driver.clone(),
+        woken: AtomicBool::new(true),
+        sleeping: AtomicBool::new(false),
+    });
+    let waker = Waker::from(unparker.clone());
+    let rearm = Waker::from(Arc::new(Rearm(unparker.clone())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    let mut completions = Vec::new();
+
+    loop {
+        if unparker.woken.swap(false, Ordering::SeqCst) {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return Ok(output);
+            }
+        }
+
+        // Fire the timers that are due. Timers added while polling are picked up here, so
+        // they only need to interrupt the loop while it sleeps.
+        let next = timers.fire(&rearm);
+
+        // Sleep until something happens, unless the future has already been woken.
+        unparker.sleeping.store(true, Ordering::SeqCst);
+        let timeout = if unparker.woken.load(Ordering::SeqCst) {
+            Some(Duration::ZERO)
+        } else {
+            next.map(|next| next.saturating_duration_since(Instant::now()))
+        };
+        let result = driver.ring.wait(timeout, &mut completions);
+        unparker.sleeping.store(false, Ordering::SeqCst);
+        result?;
+
+        driver.dispatch(&mut completions);
+    }
+}
+
+/// Marks whether the innermost reactor on this thread drives the ring, until dropped.
+///
+/// Reactors that do not use the ring enter this too, so that sources stop using the ring
+/// while they run.
+pub(crate) struct Scope(());
+
+impl Scope {
+    /// Enter a reactor that does or does not drive the ring.
+    #[inline]
+    pub(crate) fn enter(driving: bool) -> Self {
+        let changed = DRIVING.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            let changed = stack.last().copied().unwrap_or(false) != driving;
+            stack.push(driving);
+            changed
+        });
+        if changed {
+            wake_all();
+        }
+
+        Self(())
+    }
+}
+
+impl Drop for Scope {
+    #[inline]
+    fn drop(&mut self) {
+        let changed = DRIVING.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            let driving = stack.pop().unwrap_or(false);
+            stack.last().copied().unwrap_or(false) != driving
+        });
+        if changed {
+            wake_all();
+        }
+    }
+}
+
+/// Wake every source waiting on this thread's ring, so that they can move.
+#[inline]
+fn wake_all() {
+    if let Some(driver) = RING.with(|ring| ring.get().cloned().flatten()) {
+        driver.wake_all();
+    }
+}
+
+/// The ring for a thread, along with the sources waiting on it.
+pub(crate) struct Driver {
+    /// The ring.
+    ring: Ring,
+
+    /// The state of each poll, by its `user_data`.
+    waiters: Mutex<HashMap<u64, Waiter>>,
+
+    /// The token for the next source.
+    next_token: AtomicU64,
+}
+
+/// The state of one direction of a [`Source`].
+#[derive(Default)]
+struct Waiter {
+    /// The task waiting for the source to be ready.
+    waker: Option<Waker>,
+
+    /// Whether a poll is in the ring.
+    armed: bool,
+
+    /// Whether a poll has completed since this was last checked.
+    ready: bool,
+}
+
+impl Driver {
+    /// Wrap a ring.
+    #[inline]
+    fn new(ring: Ring) -> Self {
+        Self {
+            ring,
+            waiters: Mutex::new(HashMap::new()),
+            next_token: AtomicU64::new(0),
+        }
+    }
+
+    /// Check whether `fd` is ready, and poll it on the ring if it is not.
+    fn poll_ready(
+        &self,
+        fd: BorrowedFd<'_>,
+        interest: Interest,
+        user_data: u64,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut waiters = self.waiters();
+        let waiter = waiters.entry(user_data).or_default();
+        if std::mem::take(&mut waiter.ready) {
+            return Poll::Ready(Ok(()));
+        }
+
+        match &mut waiter.waker {
+            Some(waker) => waker.clone_from(cx.waker()),
+            None => waiter.waker = Some(cx.waker().clone()),
+        }
+        if !waiter.armed {
+            self.ring.poll(fd, interest, user_data)?;
+            waiter.armed = true;
+        }
+
+        Poll::Pending
+    }
+
+    /// Wake the sources whose polls have completed.
+    fn dispatch(&self, completions: &mut Vec<Completion>) {
+        let wakers = {
+            let mut waiters = self.waiters();
+            completions
+                .drain(..)
+                .filter_map(|completion| {
+                    // Errors are reported by retrying the operation on the source.
+                    let waiter = waiters.get_mut(&completion.user_data())?;
+                    waiter.armed = false;
+                    waiter.ready = true;
+                    waiter.waker.take()
+                })
+                .collect::<Vec<_>>()
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Wake every source waiting on the ring.
+    ///
+    /// Their polls stay in the ring until they are polled again.
+    fn wake_all(&self) {
+        let wakers = self
+            .waiters()
+            .values_mut()
+            .filter_map(|waiter| waiter.waker.take())
+            .collect::<Vec<_>>();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Lock the state of the polls.
+    #[inline]
+    fn waiters(&self) -> MutexGuard<'_, HashMap<u64, Waiter>> {
+        self.waiters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Wakes up the loop in [`block_on`].
+struct Unparker {
+    /// The ring the loop sleeps on.
+    driver: Arc<Driver>,
+
+    /// Whether the future needs to be polled.
+    woken: AtomicBool,
+
+    /// Whether the loop is about to sleep, or is sleeping.
+    sleeping: AtomicBool,
+}
+
+impl Unparker {
+    /// Interrupt the loop if it is sleeping.
+    #[inline]
+    fn unpark(&self) {
+        if self.sleeping.load(Ordering::SeqCst) {
+            // If this fails, the ring is broken and the loop will find out.
+            self.driver.ring.notify().ok();
+        }
+    }
+}
+
+impl Wake for Unparker {
+    #[inline]
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    #[inline]
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.woken.swap(true, Ordering::SeqCst) {
+            self.unpark();
+        }
+    }
+}
+
+/// Makes the loop in [`block_on`] check its timers again, without polling the future.
+struct Rearm(Arc<Unparker>);
+
+impl Wake for Rearm {
+    #[inline]
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// An I/O source registered with a thread's ring.
+///
+/// The source keeps a duplicate of the file descriptor, so that it can be polled without
+/// borrowing the I/O object it belongs to.
+pub(crate) struct Source {
+    /// The ring the source was registered with.
+    driver: Arc<Driver>,
+
+    /// Identifies the source's polls in the ring.
+    token: u64,
+
+    /// The duplicated file descriptor.
+    fd: Arc<OwnedFd>,
+
+    /// The registration with `async-io`, used when the ring is not being driven.
+    fallback: Mutex<Option<Arc<async_io::Async<Arc<OwnedFd>>>>>,
+}
+
+impl Source {
+    /// Register a file descriptor with a ring.
+    #[inline]
+    pub(crate) fn new(driver: Arc<Driver>, fd: BorrowedFd<'_>) -> io::Result<Self> {
+        let token = driver.next_token.fetch_add(1, Ordering::Relaxed);
+
+        Ok(Self {
+            driver,
+            token,
+            fd: Arc::new(fd.try_clone_to_owned()?),
+            fallback: Mutex::new(None),
+        })
+    }
+
+    /// Poll the source for readiness.
+    pub(crate) fn poll_ready(
+        &self,
+        interest: Interest,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        match current() {
+            Some(current) if Arc::ptr_eq(&current, &self.driver) => {
+                let user_data = self.user_data(interest);
+                self.driver
+                    .poll_ready(self.fd.as_fd(), interest, user_data, cx)
+            }
+            _ => {
+                let fallback = self.fallback()?;
+                match interest {
+                    Interest::Readable => fallback.poll_readable(cx),
+                    Interest::Writable => fallback.poll_writable(cx),
+                }
+            }
+        }
+    }
+
+    /// Get the registration with `async-io`, creating it if needed.
+    fn fallback(&self) -> io::Result<Arc<async_io::Async<Arc<OwnedFd>>>> {
+        let mut fallback = self.fallback.lock().unwrap_or_else(|e| e.into_inner());
+        match &*fallback {
+            Some(fallback) => Ok(fallback.clone()),
+            None => {
+                let registered = Arc::new(async_io::Async::new_nonblocking(self.fd.clone())?);
+                *fallback = Some(registered.clone());
+                Ok(registered)
+            }
+        }
+    }
+
+    /// Get the `user_data` of one direction of the source.
+    #[inline]
+    fn user_data(&self, interest: Interest) -> u64 {
+        let direction = match interest {
+            Interest::Readable => 0,
+            Interest::Writable => 1,
+        };
+        (self.token << 1) | direction
+    }
+}
+
+impl Drop for Source {
+    fn drop(&mut self) {
+        let mut cancelled = false;
+        {
+            let mut waiters = self.driver.waiters();
+            for interest in [Interest::Readable, Interest::Writable] {
+                let user_data = self.user_data(interest);
+                if waiters
+                    .remove(&user_data)
+                    .is_some_and(|waiter| waiter.armed)
+                {
+                    cancelled |= self.driver.ring.cancel(user_data).is_ok();
+                }
+            }
+        }
+
+        // Hand queued polls to the kernel before the file descriptor is closed, so that
+        // they never refer to a reused one.
+        if cancelled {
+            self.driver.ring.submit().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::current;
+    use crate::platform::io_uring::ReactorBuilderExt as _;
+    use crate::platform::poll_io::Async;
+    use crate::{test_reactor, Reactor, Timer};
+
+    use futures_lite::prelude::*;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::{Duration, Instant};
+
+    /// Build a reactor that runs on io_uring.
+    fn uring_reactor() -> Reactor {
+        test_reactor(|builder| builder.io_uring(true))
+    }
+
+    /// Echo everything back on a thread until the other end closes.
+    fn echo(mut stream: UnixStream) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            loop {
+                match stream.read(&mut buf).unwrap() {
+                    0 => break,
+                    n => stream.write_all(&buf[..n]).unwrap(),
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn echo_round_trips() {
+        let (left, right) = UnixStream::pair().unwrap();
+        let peer = echo(right);
+
+        uring_reactor()
+            .__block_on_result(async {
+                assert!(current().is_some());
+                let mut stream = Async::new(left)?;
+                let mut buf = [0u8; 5];
+                for _ in 0..100 {
+                    stream.write_all(b"hello").await?;
+                    stream.read_exact(&mut buf).await?;
+                    assert_eq!(&buf, b"hello");
+                }
+                Ok(())
+            })
+            .unwrap();
+        peer.join().unwrap();
+    }
+
+    #[test]
+    fn timers() {
+        let start = Instant::now();
+        uring_reactor()
+            .__block_on_result(async {
+                Timer::after(Duration::from_millis(20)).await;
+                Ok(())
+            })
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn exit_from_another_thread() {
+        let reactor = uring_reactor();
+        let handle = reactor.exit_handle();
+        let exiter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            handle.exit_with(4);
+        });
+
+        let finished = reactor
+            .block_on(std::future::pending::<std::convert::Infallible>())
+            .unwrap();
+        assert_eq!(finished.exit_code(), 4);
+        exiter.join().unwrap();
+    }
+
+    #[test]
+    fn nested_epoll_reactor() {
+        let (left, right) = UnixStream::pair().unwrap();
+        let peer = echo(right);
+
+        uring_reactor()
+            .__block_on_result(async {
+                let mut stream = Async::new(left)?;
+                let mut buf = [0u8; 3];
+
+                // The inner reactor uses epoll, so the source falls back to it.
+                test_reactor(|builder| builder).__block_on_result(async {
+                    assert!(current().is_none());
+                    stream.write_all(b"abc").await?;
+                    stream.read_exact(&mut buf).await?;
+                    Ok(())
+                })?;
+                assert_eq!(&buf, b"abc");
+
+                // The source moves back to the ring afterwards.
+                assert!(current().is_some());
+                stream.write_all(b"def").await?;
+                stream.read_exact(&mut buf).await?;
+                assert_eq!(&buf, b"def");
+                Ok(())
+            })
+            .unwrap();
+        peer.join().unwrap();
+    }
+
+    #[test]
+    fn source_outlives_reactor() {
+        let (left, right) = UnixStream::pair().unwrap();
+        let peer = echo(right);
+
+        let mut stream = None;
+        uring_reactor()
+            .__block_on_result(async {
+                stream = Some(Async::new(left)?);
+                Ok(())
+            })
+            .unwrap();
+
+        // Once the reactor stops, the source is polled with `async-io`.
+        let mut stream = stream.unwrap();
+        let mut buf = [0u8; 2];
+        futures_lite::future::block_on(async {
+            stream.write_all(b"hi").await?;
+            stream.read_exact(&mut buf).await
+        })
+        .unwrap();
+        assert_eq!(&buf, b"hi");
+        drop(stream);
+        peer.join().unwrap();
+    }
+}
@@ -0,0 +1,557 @@
+// MIT/Apache2 License
+
+//! A hierarchical timer wheel, driven by the reactor.
+//!
+//! Instead of every timer registering with the system reactor, timers are stored in a
+//! wheel owned by the reactor running on the current thread. The reactor then only needs
+//! one system timer, set for the earliest deadline in the wheel, and all timers that are
+//! due are fired in a single wakeup.
+
+use std::cell::RefCell;
+use std::mem;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Poll, Waker};
+
+use futures_lite::prelude::*;
+use web_time::{Duration, Instant};
+
+/// The number of bits used to index a slot in a level.
+const SLOT_BITS: u32 = 6;
+
+/// The number of slots in each level.
+const SLOTS: usize = 1 << SLOT_BITS;
+
+/// The number of levels in the wheel.
+const LEVELS: usize = 6;
+
+/// The furthest ahead, in ticks, that the wheel can sort a timer.
+///
+/// Timers further away than this (about two years) are kept in the last level and sorted
+/// again when it comes around.
+const MAX_TICKS: u64 = 1 << (SLOT_BITS as usize * LEVELS);
+
+/// The length of a tick, in nanoseconds.
+///
+/// This must stay one millisecond, since ticks are converted to and from milliseconds.
+const TICK_NANOS: u64 = 1_000_000;
+
+thread_local! {
+    /// The wheel for this thread, along with the number of reactors driving it.
+    static LOCAL: (Arc<Queue>, RefCell<usize>) = (Arc::new(Queue::new()), RefCell::new(0));
+}
+
+/// Get the wheel for this thread, if a reactor is driving it.
+#[inline]
+pub(crate) fn current() -> Option<Arc<Queue>> {
+    LOCAL.with(|(queue, driving)| (*driving.borrow() > 0).then(|| queue.clone()))
+}
+
+/// Drive the wheel for this thread until the future is dropped.
+pub(crate) async fn drive() {
+    let driver = Driver::enter();
+    let mut sleep = async_io::Timer::never();
+
+    futures_lite::future::poll_fn(|cx| loop {
+        match driver.fire(cx.waker()) {
+            Some(next) => sleep.set_at(next),
+            None => sleep = async_io::Timer::never(),
+        }
+        if Pin::new(&mut sleep).poll(cx).is_pending() {
+            return Poll::<()>::Pending;
+        }
+    })
+    .await
+}
+
+/// Fires the timers in the wheel for this thread until dropped.
+///
+/// Reactors with their own event loop use this directly instead of [`drive`].
+pub(crate) struct Driver {
+    /// The wheel being driven.
+    queue: Arc<Queue>,
+
+    /// Marks the wheel as driven.
+    _driving: Driving,
+}
+
+impl Driver {
+    /// Start driving the wheel for this thread.
+    #[inline]
+    pub(crate) fn enter() -> Self {
+        let (queue, driving) = Driving::enter();
+        Self {
+            queue,
+            _driving: driving,
+        }
+    }
+
+    /// Fire every timer that is due, and return when the next one is.
+    ///
+    /// `waker` is woken when a timer that is due sooner is added.
+    pub(crate) fn fire(&self, waker: &Waker) -> Option<Instant> {
+        let (fired, next) = {
+            let mut state = self.queue.state();
+            if !state.drivers.iter().any(|w| w.will_wake(waker)) {
+                state.drivers.push(waker.clone());
+            }
+            let fired = state.wheel.advance(Instant::now());
+            let next = state.wheel.next_deadline();
+            state.armed = next;
+            (fired, next)
+        };
+        fired.into_iter().for_each(Waker::wake);
+        next
+    }
+}
+
+/// Marks the wheel for this thread as driven until dropped.
+struct Driving(());
+
+impl Driving {
+    /// Start driving the wheel for this thread.
+    #[inline]
+    fn enter() -> (Arc<Queue>, Self) {
+        LOCAL.with(|(queue, driving)| {
+            *driving.borrow_mut() += 1;
+            (queue.clone(), Driving(()))
+        })
+    }
+}
+
+impl Drop for Driving {
+    #[inline]
+    fn drop(&mut self) {
+        LOCAL.with(|(queue, driving)| {
+            let mut driving = driving.borrow_mut();
+            *driving -= 1;
+
+            if *driving == 0 {
+                // Nothing fires these timers anymore, so they need to move elsewhere.
+                queue.wake_all();
+            } else {
+                // Reactors further out may have to pick up timers registered in the meantime.
+                queue.wake_drivers(queue.state());
+            }
+        });
+    }
+}
+
+/// A timer wheel shared between the reactor and its timers.
+pub(crate) struct Queue {
+    /// The state of the queue.
+    state: Mutex<QueueState>,
+}
+
+/// The state of a [`Queue`].
+struct QueueState {
+    /// The wheel itself.
+    wheel: Wheel,
+
+    /// The reactors driving the wheel.
+    drivers: Vec<Waker>,
+
+    /// The deadline that the drivers are waiting for.
+    armed: Option<Instant>,
+}
+
+impl Queue {
+    /// Create an empty queue.
+    #[inline]
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                wheel: Wheel::new(Instant::now()),
+                drivers: Vec::new(),
+                armed: None,
+            }),
+        }
+    }
+
+    /// Register a timer.
+    #[inline]
+    pub(crate) fn insert(&self, when: Instant, waker: &Waker) -> Key {
+        let mut state = self.state();
+        let key = state.wheel.insert(when, waker.clone());
+
+        // Tell the drivers if they need to wake up sooner.
+        if state.wheel.next_deadline() != state.armed {
+            self.wake_drivers(state);
+        }
+
+        key
+    }
+
+    /// Remove a timer, returning its waker if it has not fired.
+    #[inline]
+    pub(crate) fn remove(&self, key: Key) -> Option<Waker> {
+        self.state().wheel.remove(key)
+    }
+
+    /// Replace the waker of a timer.
+    ///
+    /// Returns `false` if the timer has already fired.
+    #[inline]
+    pub(crate) fn update_waker(&self, key: Key, waker: &Waker) -> bool {
+        self.state().wheel.update_waker(key, waker)
+    }
+
+    /// Get the earliest deadline in the wheel.
+    #[inline]
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.state().wheel.next_deadline()
+    }
+
+    /// Wake up every timer in the wheel.
+    ///
+    /// The timers stay registered until they are polled again.
+    #[inline]
+    fn wake_all(&self) {
+        let wakers = self.state().wheel.wakers();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Wake up the reactors driving the wheel.
+    #[inline]
+    fn wake_drivers(&self, mut state: MutexGuard<'_, QueueState>) {
+        let drivers = mem::take(&mut state.drivers);
+        drop(state);
+        drivers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Lock the state of the queue.
+    #[inline]
+    fn state(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Identifies a timer in a [`Wheel`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Key {
+    /// The index of the entry.
+    index: usize,
+
+    /// The generation of the entry, to catch entries that were reused.
+    generation: u64,
+}
+
+/// A hierarchical timer wheel.
+///
+/// Level `n` has 64 slots that each cover `64^n` ticks. A timer is stored in the lowest
+/// level where its deadline falls in a slot ahead of the current time. When the wheel
+/// reaches a slot in a higher level, its timers are sorted into the lower levels.
+pub(crate) struct Wheel {
+    /// The time that tick zero starts at.
+    start: Instant,
+
+    /// The current tick.
+    elapsed: u64,
+
+    /// The levels of the wheel, finest first.
+    levels: [Level; LEVELS],
+
+    /// Storage for timers.
+    entries: Vec<Entry>,
+
+    /// Indices of unused entries.
+    free: Vec<usize>,
+}
+
+/// One level of a [`Wheel`].
+struct Level {
+    /// A bit set for every slot that has timers in it.
+    occupied: u64,
+
+    /// The indices of the entries in each slot.
+    slots: [Vec<usize>; SLOTS],
+}
+
+/// A timer in a [`Wheel`].
+struct Entry {
+    /// Incremented every time this entry is freed.
+    generation: u64,
+
+    /// The tick the timer fires at.
+    tick: u64,
+
+    /// The waker of the timer, or `None` if the entry is unused.
+    waker: Option<Waker>,
+
+    /// The level the timer is stored in.
+    level: usize,
+
+    /// The slot the timer is stored in.
+    slot: usize,
+
+    /// The position of the timer in its slot.
+    position: usize,
+}
+
+impl Wheel {
+    /// Create an empty wheel starting at the given time.
+    pub(crate) fn new(start: Instant) -> Self {
+        Self {
+            start,
+            elapsed: 0,
+            levels: std::array::from_fn(|_| Level {
+                occupied: 0,
+                slots: std::array::from_fn(|_| Vec::new()),
+            }),
+            entries: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Add a timer that fires at `when`.
+    pub(crate) fn insert(&mut self, when: Instant, waker: Waker) -> Key {
+        // Round up, so that timers never fire early.
+        let nanos = when.saturating_duration_since(self.start).as_nanos();
+        let tick = u64::try_from(nanos.div_ceil(TICK_NANOS as u128))
+            .unwrap_or(u64::MAX)
+            .max(self.elapsed);
+
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.entries.push(Entry {
+                    generation: 0,
+                    tick: 0,
+                    waker: None,
+                    level: 0,
+                    slot: 0,
+                    position: 0,
+                });
+                self.entries.len() - 1
+            }
+        };
+
+        let entry = &mut self.entries[index];
+        entry.tick = tick;
+        entry.waker = Some(waker);
+        let generation = entry.generation;
+        self.link(index);
+
+        Key { index, generation }
+    }
+
+    /// Remove a timer, returning its waker if it has not fired.
+    pub(crate) fn remove(&mut self, key: Key) -> Option<Waker> {
+        self.entry(key)?;
+        self.unlink(key.index);
+        self.release(key.index)
+    }
+
+    /// Replace the waker of a timer.
+    ///
+    /// Returns `false` if the timer has already fired.
+    pub(crate) fn update_waker(&mut self, key: Key, waker: &Waker) -> bool {
+        match self.entry(key).and_then(|entry| entry.waker.as_mut()) {
+            Some(old) => {
+                if !old.will_wake(waker) {
+                    *old = waker.clone();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the time that the next timer fires at.
+    #[inline]
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        let (_, _, tick) = self.next_expiration()?;
+        self.start.checked_add(Duration::from_millis(tick))
+    }
+
+    /// Get the wakers of every timer in the wheel.
+    pub(crate) fn wakers(&self) -> Vec<Waker> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.waker.clone())
+            .collect()
+    }
+
+    /// Move the wheel forward to `now`, returning the wakers of timers that are due.
+    pub(crate) fn advance(&mut self, now: Instant) -> Vec<Waker> {
+        let target = u64::try_from(now.saturating_duration_since(self.start).as_millis())
+            .unwrap_or(u64::MAX);
+        let mut fired = Vec::new();
+
+        while let Some((level, slot, tick)) = self.next_expiration() {
+            if tick > target {
+                break;
+            }
+
+            // Fire the timers in this slot, and sort the rest into lower levels.
+            self.elapsed = tick;
+            let indices = mem::take(&mut self.levels[level].slots[slot]);
+            self.levels[level].occupied &= !(1 << slot);
+            for index in indices {
+                if self.entries[index].tick <= self.elapsed {
+                    fired.extend(self.release(index));
+                } else {
+                    self.link(index);
+                }
+            }
+        }
+
+        self.elapsed = self.elapsed.max(target);
+        fired
+    }
+
+    /// Find the level, slot and starting tick of the next slot with timers in it.
+    fn next_expiration(&self) -> Option<(usize, usize, u64)> {
+        // Lower levels always expire before higher levels.
+        self.levels.iter().enumerate().find_map(|(level, slots)| {
+            if slots.occupied == 0 {
+                return None;
+            }
+
+            let shift = SLOT_BITS * level as u32;
+            let slot_range = 1u64 << shift;
+            let level_range = slot_range << SLOT_BITS;
+            let current = (self.elapsed >> shift) as usize % SLOTS;
+
+            // Find the first occupied slot at or after the current one.
+            let offset = slots.occupied.rotate_right(current as u32).trailing_zeros() as usize;
+            let slot = (current + offset) % SLOTS;
+
+            let level_start = self.elapsed & !(level_range - 1);
+            let mut tick = level_start + slot as u64 * slot_range;
+            if tick < self.elapsed {
+                // The slot is in the next rotation of this level.
+                tick = tick.saturating_add(level_range);
+            }
+
+            Some((level, slot, tick))
+        })
+    }
+
+    /// Store an entry in the slot for its tick.
+    fn link(&mut self, index: usize) {
+        // Timers too far in the future are sorted again later.
+        let tick = self.entries[index]
+            .tick
+            .min(self.elapsed.saturating_add(MAX_TICKS - 1));
+
+        let significant = 63 - ((self.elapsed ^ tick) | (SLOTS as u64 - 1)).leading_zeros();
+        let level = (significant / SLOT_BITS).min(LEVELS as u32 - 1);
+        let slot = (tick >> (SLOT_BITS * level)) as usize % SLOTS;
+        let level = level as usize;
+
+        let slots = &mut self.levels[level];
+        slots.occupied |= 1 << slot;
+        slots.slots[slot].push(index);
+
+        let entry = &mut self.entries[index];
+        entry.level = level;
+        entry.slot = slot;
+        entry.position = slots.slots[slot].len() - 1;
+    }
+
+    /// Remove an entry from its slot.
+    fn unlink(&mut self, index: usize) {
+        let Entry {
+            level,
+            slot,
+            position,
+            ..
+        } = self.entries[index];
+        let slots = &mut self.levels[level];
+        let list = &mut slots.slots[slot];
+
+        list.swap_remove(position);
+        if let Some(&moved) = list.get(position) {
+            self.entries[moved].position = position;
+        }
+        if list.is_empty() {
+            slots.occupied &= !(1 << slot);
+        }
+    }
+
+    /// Mark an unlinked entry as unused, returning its waker.
+    #[inline]
+    fn release(&mut self, index: usize) -> Option<Waker> {
+        let entry = &mut self.entries[index];
+        entry.generation += 1;
+        self.free.push(index);
+        entry.waker.take()
+    }
+
+    /// Get the entry for a key, if it is still in use.
+    #[inline]
+    fn entry(&mut self, key: Key) -> Option<&mut Entry> {
+        self.entries
+            .get_mut(key.index)
+            .filter(|entry| entry.generation == key.generation && entry.waker.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    #[test]
+    fn timer_wheel() {
+        struct Counter(AtomicUsize);
+
+        impl Wake for Counter {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let ms = Duration::from_millis;
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        let fire = |wakers: Vec<Waker>| {
+            let count = wakers.len();
+            wakers.into_iter().for_each(Waker::wake);
+            count
+        };
+
+        let start = Instant::now();
+        let mut wheel = Wheel::new(start);
+        assert_eq!(wheel.next_deadline(), None);
+
+        // Timers in different levels of the wheel.
+        wheel.insert(start + ms(5), waker.clone());
+        let cancelled = wheel.insert(start + ms(70), waker.clone());
+        wheel.insert(start + ms(5_000), waker.clone());
+        wheel.insert(
+            start + Duration::from_secs(60 * 60 * 24 * 365 * 5),
+            waker.clone(),
+        );
+        assert_eq!(wheel.next_deadline(), Some(start + ms(5)));
+
+        // Nothing fires early.
+        assert_eq!(fire(wheel.advance(start + ms(4))), 0);
+        assert_eq!(fire(wheel.advance(start + ms(5))), 1);
+
+        // Cancelled timers never fire, and their keys go stale.
+        assert!(wheel.remove(cancelled).is_some());
+        assert!(wheel.remove(cancelled).is_none());
+        assert!(!wheel.update_waker(cancelled, &waker));
+
+        // Higher levels are sorted into lower ones as time passes.
+        assert_eq!(fire(wheel.advance(start + ms(4_999))), 0);
+        assert_eq!(wheel.next_deadline(), Some(start + ms(5_000)));
+        assert_eq!(fire(wheel.advance(start + ms(5_000))), 1);
+
+        // Overdue timers fire on the next advance.
+        wheel.insert(start, waker.clone());
+        assert_eq!(fire(wheel.advance(start + ms(5_000))), 1);
+
+        // Timers beyond the reach of the wheel still fire eventually.
+        assert!(wheel.next_deadline().is_some());
+        let far = start + Duration::from_secs(60 * 60 * 24 * 365 * 5);
+        assert_eq!(fire(wheel.advance(far - ms(1))), 0);
+        assert_eq!(fire(wheel.advance(far)), 1);
+        assert_eq!(wheel.next_deadline(), None);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 4);
+    }
+}
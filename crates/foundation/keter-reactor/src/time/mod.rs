@@ -0,0 +1,376 @@
+// MIT/Apache2 License
+
+//! Utilities for working with time in the reactor.
+
+mod stream;
+mod timeout;
+
+pub use stream::{Debounce, Sample, StreamTimeExt, Throttle};
+pub use timeout::{timeout, timeout_at, Deadline, Elapsed, Timeout};
+
+use crate::timer_queue::{QueuedTimer, TimerQueue};
+
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use web_time::{Duration, Instant};
+
+thread_local! {
+    /// The virtual clock of the innermost reactor running on this thread, if it has one.
+    static CLOCK: RefCell<Option<VirtualClock>> = const { RefCell::new(None) };
+}
+
+/// Get the virtual clock of the innermost reactor running on this thread.
+#[inline]
+pub(crate) fn current_clock() -> Option<VirtualClock> {
+    CLOCK.with(|clock| clock.borrow().clone())
+}
+
+/// Get the current time according to the reactor running on this thread.
+///
+/// This is the time on the reactor's [`VirtualClock`] if it has one, and the real time
+/// otherwise. Timers compare their deadlines against this time.
+#[inline]
+pub fn now() -> Instant {
+    match current_clock() {
+        Some(clock) => clock.now(),
+        None => Instant::now(),
+    }
+}
+
+/// Get the deadline of the next timer of the reactor running on this thread, if known.
+#[inline]
+pub(crate) fn next_deadline() -> Option<Instant> {
+    match current_clock() {
+        Some(clock) => clock.next_deadline(),
+        None => crate::sys::next_deadline(),
+    }
+}
+
+/// Move the current virtual clock to its next timer, if it advances automatically.
+///
+/// Returns `true` if the clock was advanced.
+#[inline]
+pub(crate) fn auto_advance() -> bool {
+    match current_clock() {
+        Some(clock) if clock.auto_advance() => match clock.next_deadline() {
+            Some(deadline) => {
+                // Simulations decide the order that the timers are woken in.
+                clock
+                    .take_due(deadline)
+                    .into_iter()
+                    .for_each(crate::sim::wake);
+                true
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Sets the virtual clock for this thread until dropped.
+pub(crate) struct ClockScope(Option<VirtualClock>);
+
+impl ClockScope {
+    /// Use the given clock until the scope is dropped.
+    #[inline]
+    pub(crate) fn enter(clock: Option<VirtualClock>) -> Self {
+        Self(CLOCK.with(|current| current.replace(clock)))
+    }
+}
+
+impl Drop for ClockScope {
+    #[inline]
+    fn drop(&mut self) {
+        CLOCK.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+/// A clock that only moves forward when told to.
+///
+/// A reactor built with [`ReactorBuilder::virtual_clock`] runs its timers against this
+/// clock instead of the real time. This makes it possible to test code that uses timers
+/// instantly and deterministically.
+///
+/// The clock can be advanced manually through [`VirtualClock::advance`], or set to advance
+/// automatically to the next timer whenever the reactor is idle.
+///
+/// [`ReactorBuilder::virtual_clock`]: crate::ReactorBuilder::virtual_clock
+#[derive(Clone)]
+pub struct VirtualClock(Arc<Inner>);
+
+/// The shared state of a [`VirtualClock`].
+struct Inner {
+    /// The real time the clock was created at.
+    start: Instant,
+
+    /// Whether to advance to the next timer when the reactor is idle.
+    auto_advance: AtomicBool,
+
+    /// How far the clock has been advanced.
+    elapsed: Mutex<Duration>,
+
+    /// Timers registered against this clock.
+    timers: TimerQueue,
+}
+
+impl fmt::Debug for VirtualClock {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VirtualClock")
+            .field("elapsed", &self.elapsed())
+            .field("auto_advance", &self.auto_advance())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for VirtualClock {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VirtualClock {
+    /// Create a new clock, starting at the current real time.
+    #[inline]
+    pub fn new() -> Self {
+        Self(Arc::new(Inner {
+            start: Instant::now(),
+            auto_advance: AtomicBool::new(false),
+            elapsed: Mutex::new(Duration::ZERO),
+            timers: TimerQueue::new(),
+        }))
+    }
+
+    /// Make the clock advance automatically to the next timer whenever the reactor is idle.
+    #[inline]
+    pub fn with_auto_advance(self, auto_advance: bool) -> Self {
+        self.set_auto_advance(auto_advance);
+        self
+    }
+
+    /// Set whether the clock advances automatically to the next timer whenever the reactor
+    /// is idle.
+    #[inline]
+    pub fn set_auto_advance(&self, auto_advance: bool) {
+        self.0.auto_advance.store(auto_advance, Ordering::Relaxed);
+    }
+
+    /// Tell whether the clock advances automatically.
+    #[inline]
+    pub fn auto_advance(&self) -> bool {
+        self.0.auto_advance.load(Ordering::Relaxed)
+    }
+
+    /// Get the current time on this clock.
+    #[inline]
+    pub fn now(&self) -> Instant {
+        self.0.start + self.elapsed()
+    }
+
+    /// Get how far this clock has been advanced since it was created.
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        *self.0.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Move the clock forward, firing any timers that become due.
+    #[inline]
+    pub fn advance(&self, duration: Duration) {
+        let now = self.now();
+        self.advance_to(now.checked_add(duration).unwrap_or(now));
+    }
+
+    /// Move the clock forward to a specific time, firing any timers that become due.
+    ///
+    /// The clock never moves backwards; times in the past only fire overdue timers.
+    pub fn advance_to(&self, time: Instant) {
+        // Wake the timers outside of the lock, since wakers may re-register them.
+        self.take_due(time).into_iter().for_each(Waker::wake);
+    }
+
+    /// Move the clock forward to a specific time, returning the wakers of timers that
+    /// become due.
+    fn take_due(&self, time: Instant) -> Vec<Waker> {
+        {
+            let mut elapsed = self.0.elapsed.lock().unwrap_or_else(|e| e.into_inner());
+            *elapsed = (*elapsed).max(time.saturating_duration_since(self.0.start));
+        }
+
+        self.0.timers.take_due(time)
+    }
+
+    /// Get the deadline of the next timer on this clock.
+    #[inline]
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.0.timers.next_deadline()
+    }
+}
+
+/// The timer queue of a [`VirtualClock`].
+struct ClockTimers(VirtualClock);
+
+impl Deref for ClockTimers {
+    type Target = TimerQueue;
+
+    #[inline]
+    fn deref(&self) -> &TimerQueue {
+        &self.0 .0.timers
+    }
+}
+
+/// A timer that runs against a [`VirtualClock`].
+pub(crate) struct VirtualTimer(QueuedTimer<ClockTimers>);
+
+impl VirtualTimer {
+    /// Create a timer that never fires.
+    #[inline]
+    pub(crate) fn new(clock: VirtualClock) -> Self {
+        Self(QueuedTimer::new(ClockTimers(clock)))
+    }
+
+    /// Set this timer to never fire.
+    #[inline]
+    pub(crate) fn set_never(&mut self) {
+        self.0.set_never();
+    }
+
+    /// Set this timer to fire repeatedly, starting at `at`.
+    #[inline]
+    pub(crate) fn set_interval(&mut self, at: Instant, period: Duration) {
+        self.0.set_interval(at, period);
+    }
+
+    /// Get the next time this timer fires.
+    #[inline]
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.0.deadline()
+    }
+
+    /// Get the period of this timer, if it fires repeatedly.
+    #[inline]
+    pub(crate) fn period(&self) -> Option<Duration> {
+        self.0.period()
+    }
+
+    /// Wait for the next time this timer fires.
+    #[inline]
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let now = self.0.queue().0.now();
+        self.0.poll(now, cx)
+    }
+}
+
+#[cfg(all(test, unix, not(target_vendor = "apple"), not(target_os = "android")))]
+mod tests {
+    use super::*;
+    use crate::{test_reactor, Timer};
+
+    use futures_lite::{future, StreamExt};
+
+    #[test]
+    fn virtual_clock_auto_advance() {
+        let clock = VirtualClock::new().with_auto_advance(true);
+        let reactor = test_reactor(|builder| builder.virtual_clock(clock.clone()));
+
+        let real_start = std::time::Instant::now();
+        reactor
+            .__block_on_result(async {
+                let start = now();
+                let at = |secs| start + Duration::from_secs(secs);
+
+                // One-shot timers fire exactly at their deadline.
+                Timer::after(Duration::from_secs(60)).await;
+                assert_eq!(now(), at(60));
+
+                // Intervals keep their phase.
+                let mut timer = Timer::interval_at(at(100), Duration::from_secs(10));
+                timer.next().await;
+                assert_eq!(now(), at(100));
+                timer.next().await;
+                assert_eq!(now(), at(110));
+
+                // Resetting a timer replaces its deadline.
+                timer.set_after(Duration::from_secs(5));
+                timer.next().await;
+                assert_eq!(now(), at(115));
+                timer.set_at(at(200));
+                timer.next().await;
+                assert_eq!(now(), at(200));
+                timer.set_interval(Duration::from_secs(3));
+                timer.next().await;
+                timer.next().await;
+                assert_eq!(now(), at(206));
+                timer.set_interval_at(at(300), Duration::from_secs(1));
+                timer.next().await;
+                assert_eq!(now(), at(300));
+
+                // The earliest of several timers fires first.
+                let first = future::or(
+                    async {
+                        Timer::after(Duration::from_secs(2)).await;
+                        1
+                    },
+                    async {
+                        Timer::after(Duration::from_secs(1)).await;
+                        2
+                    },
+                );
+                assert_eq!(first.await, 2);
+                assert_eq!(now(), at(301));
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(clock.elapsed(), Duration::from_secs(301));
+        assert!(real_start.elapsed() < Duration::from_secs(60));
+    }
+
+    #[test]
+    fn virtual_clock_manual() {
+        use crate::FrameClock;
+
+        let clock = VirtualClock::new();
+        let reactor = test_reactor(|builder| builder.virtual_clock(clock.clone()));
+
+        reactor
+            .__block_on_result(async {
+                let start = now();
+
+                // Timers only fire once the clock reaches them.
+                let mut timer = Timer::after(Duration::from_millis(10));
+                assert!(future::poll_once(&mut timer).await.is_none());
+                clock.advance(Duration::from_millis(9));
+                assert!(future::poll_once(&mut timer).await.is_none());
+                clock.advance(Duration::from_millis(1));
+                assert!(future::poll_once(&mut timer).await.is_some());
+
+                // Advancing wakes the task waiting on the timer.
+                let mut timer = Timer::after(Duration::from_millis(10));
+                let advance = async {
+                    future::yield_now().await;
+                    clock.advance(Duration::from_millis(10));
+                    std::future::pending::<()>().await
+                };
+                future::or(&mut timer, advance).await;
+                assert_eq!(now(), start + Duration::from_millis(20));
+
+                // Frame clocks report frames missed while the clock jumped.
+                let mut frames = FrameClock::new(100);
+                let first = frames.next().await.unwrap();
+                assert_eq!(first.time(), start + Duration::from_millis(20));
+                clock.advance(Duration::from_millis(35));
+                let frame = frames.next().await.unwrap();
+                assert_eq!(frame.missed(), 2);
+                assert_eq!(frame.time(), start + Duration::from_millis(50));
+                assert_eq!(frames.stats().last_frame_time(), Duration::from_millis(35));
+                Ok(())
+            })
+            .unwrap();
+    }
+}
@@ -0,0 +1,320 @@
+// MIT/Apache2 License
+
+//! Rate limiting for streams.
+
+use crate::Timer;
+
+use futures_core::stream::Stream;
+use pin_project_lite::pin_project;
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use web_time::Duration;
+
+/// Extensions for [`Stream`]s that limit how often items are produced.
+///
+/// These are meant for bursty sources like input events, where only some of the items
+/// need to be handled.
+pub trait StreamTimeExt: Stream {
+    /// Produce at most one item per `period`.
+    ///
+    /// The first item is produced right away. Items that arrive within `period` of the
+    /// last produced item are held back, and only the latest of them is produced once the
+    /// period ends.
+    #[inline]
+    fn throttle(self, period: Duration) -> Throttle<Self>
+    where
+        Self: Sized,
+    {
+        Throttle {
+            stream: self,
+            timer: Timer::never(),
+            period,
+            cooling: false,
+            held: None,
+            done: false,
+        }
+    }
+
+    /// Produce an item only once the stream has been quiet for `quiet`.
+    ///
+    /// Only the latest item in a burst is produced.
+    #[inline]
+    fn debounce(self, quiet: Duration) -> Debounce<Self>
+    where
+        Self: Sized,
+    {
+        Debounce {
+            stream: self,
+            timer: Timer::never(),
+            quiet,
+            held: None,
+            done: false,
+        }
+    }
+
+    /// Produce the latest item once every `period`.
+    ///
+    /// Nothing is produced for a period where no items arrived.
+    #[inline]
+    fn sample(self, period: Duration) -> Sample<Self>
+    where
+        Self: Sized,
+    {
+        Sample {
+            stream: self,
+            timer: Timer::interval(period),
+            latest: None,
+            done: false,
+        }
+    }
+}
+
+impl<S: Stream + ?Sized> StreamTimeExt for S {}
+
+pin_project! {
+    /// A stream that produces at most one item per period.
+    ///
+    /// This is returned by [`StreamTimeExt::throttle`].
+    #[must_use = "streams do nothing unless polled"]
+    pub struct Throttle<S: Stream> {
+        #[pin]
+        stream: S,
+
+        // Fires when the current period ends.
+        timer: Timer,
+
+        // The time between items.
+        period: Duration,
+
+        // Whether an item was produced in the current period.
+        cooling: bool,
+
+        // The latest item held back in the current period.
+        held: Option<S::Item>,
+
+        // Whether the inner stream has ended.
+        done: bool,
+    }
+}
+
+impl<S: Stream + fmt::Debug> fmt::Debug for Throttle<S> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Throttle")
+            .field("stream", &self.stream)
+            .field("period", &self.period)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Stream> Stream for Throttle<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        while !*this.done {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) if !*this.cooling => {
+                    // Produce the item and start a new period.
+                    *this.cooling = true;
+                    this.timer.set_after(*this.period);
+                    return Poll::Ready(Some(item));
+                }
+                Poll::Ready(Some(item)) => *this.held = Some(item),
+                Poll::Ready(None) => *this.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        // Flush the held item once the stream ends.
+        if *this.done {
+            return Poll::Ready(this.held.take());
+        }
+
+        if *this.cooling && Pin::new(&mut *this.timer).poll(cx).is_ready() {
+            match this.held.take() {
+                Some(item) => {
+                    this.timer.set_after(*this.period);
+                    return Poll::Ready(Some(item));
+                }
+                None => *this.cooling = false,
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+pin_project! {
+    /// A stream that produces an item once its source has been quiet for a while.
+    ///
+    /// This is returned by [`StreamTimeExt::debounce`].
+    #[must_use = "streams do nothing unless polled"]
+    pub struct Debounce<S: Stream> {
+        #[pin]
+        stream: S,
+
+        // Fires once the stream has been quiet for long enough.
+        timer: Timer,
+
+        // How long the stream must be quiet for.
+        quiet: Duration,
+
+        // The latest item.
+        held: Option<S::Item>,
+
+        // Whether the inner stream has ended.
+        done: bool,
+    }
+}
+
+impl<S: Stream + fmt::Debug> fmt::Debug for Debounce<S> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debounce")
+            .field("stream", &self.stream)
+            .field("quiet", &self.quiet)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Stream> Stream for Debounce<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        while !*this.done {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    // Wait for the stream to be quiet again.
+                    *this.held = Some(item);
+                    this.timer.set_after(*this.quiet);
+                }
+                Poll::Ready(None) => *this.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        // Flush the held item once the stream ends.
+        if *this.done {
+            return Poll::Ready(this.held.take());
+        }
+
+        if this.held.is_some() && Pin::new(&mut *this.timer).poll(cx).is_ready() {
+            return Poll::Ready(this.held.take());
+        }
+
+        Poll::Pending
+    }
+}
+
+pin_project! {
+    /// A stream that produces the latest item once every period.
+    ///
+    /// This is returned by [`StreamTimeExt::sample`].
+    #[must_use = "streams do nothing unless polled"]
+    pub struct Sample<S: Stream> {
+        #[pin]
+        stream: S,
+
+        // Fires at the end of every period.
+        timer: Timer,
+
+        // The latest item in the current period.
+        latest: Option<S::Item>,
+
+        // Whether the inner stream has ended.
+        done: bool,
+    }
+}
+
+impl<S: Stream + fmt::Debug> fmt::Debug for Sample<S> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sample")
+            .field("stream", &self.stream)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Stream> Stream for Sample<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        while !*this.done {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => *this.latest = Some(item),
+                Poll::Ready(None) => *this.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        // Flush the latest item once the stream ends.
+        if *this.done {
+            return Poll::Ready(this.latest.take());
+        }
+
+        while Pin::new(&mut *this.timer).poll(cx).is_ready() {
+            if let Some(item) = this.latest.take() {
+                return Poll::Ready(Some(item));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(all(test, unix, not(target_vendor = "apple"), not(target_os = "android")))]
+mod tests {
+    use super::*;
+    use crate::time::{self, VirtualClock};
+    use crate::{spawn_local, test_reactor};
+
+    use futures_lite::StreamExt;
+
+    /// Send items at the given times, in milliseconds from now.
+    fn events(times: &'static [u64]) -> async_channel::Receiver<u64> {
+        let (send, recv) = async_channel::unbounded();
+        let start = time::now();
+        spawn_local(async move {
+            for &at in times {
+                Timer::at(start + Duration::from_millis(at)).await;
+                send.send(at).await.ok();
+            }
+        })
+        .detach();
+        recv
+    }
+
+    #[test]
+    fn rate_limiting() {
+        let reactor = test_reactor(|builder| {
+            builder.virtual_clock(VirtualClock::new().with_auto_advance(true))
+        });
+
+        reactor
+            .__block_on_result(async {
+                let ms = Duration::from_millis;
+                let times = &[0, 2, 4, 12, 30, 31];
+
+                let throttled = events(times).throttle(ms(10)).collect::<Vec<_>>().await;
+                assert_eq!(throttled, [0, 4, 12, 30, 31]);
+
+                let debounced = events(times).debounce(ms(5)).collect::<Vec<_>>().await;
+                assert_eq!(debounced, [4, 12, 31]);
+
+                let sampled = events(times).sample(ms(10)).collect::<Vec<_>>().await;
+                assert_eq!(sampled, [4, 12, 31]);
+                Ok(())
+            })
+            .unwrap();
+    }
+}
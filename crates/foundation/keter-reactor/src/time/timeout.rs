@@ -0,0 +1,286 @@
+// MIT/Apache2 License
+
+//! Running futures with a time limit.
+
+use crate::Timer;
+
+use pin_project_lite::pin_project;
+
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use web_time::{Duration, Instant};
+
+/// Run a future, giving up if it takes longer than `duration`.
+///
+/// ## Example
+///
+/// ```no_run
+/// use keter_reactor::time::timeout;
+/// use std::time::Duration;
+///
+/// # async fn f() {
+/// let result = timeout(Duration::from_secs(1), std::future::pending::<()>()).await;
+/// assert!(result.is_err());
+/// # }
+/// ```
+#[inline]
+pub fn timeout<F: Future>(duration: Duration, future: F) -> Timeout<F> {
+    Timeout {
+        future,
+        timer: Timer::after(duration),
+        deadline: None,
+    }
+}
+
+/// Run a future, giving up if it is still running at `deadline`.
+#[inline]
+pub fn timeout_at<F: Future>(deadline: Instant, future: F) -> Timeout<F> {
+    Timeout {
+        future,
+        timer: Timer::at(deadline),
+        deadline: None,
+    }
+}
+
+/// The error returned when a future runs out of time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed(());
+
+impl fmt::Display for Elapsed {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+impl Error for Elapsed {}
+
+impl From<Elapsed> for io::Error {
+    #[inline]
+    fn from(elapsed: Elapsed) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, elapsed)
+    }
+}
+
+pin_project! {
+    /// A future that runs another future with a time limit.
+    ///
+    /// This is returned by [`timeout`], [`timeout_at`] and [`Deadline::timeout`].
+    pub struct Timeout<F> {
+        #[pin]
+        future: F,
+
+        // Fires at the deadline, as far as we know it.
+        timer: Timer,
+
+        // The shared deadline, if it can be extended.
+        deadline: Option<Deadline>,
+    }
+}
+
+impl<F: fmt::Debug> fmt::Debug for Timeout<F> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timeout")
+            .field("future", &self.future)
+            .field("deadline", &self.deadline)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F> Timeout<F> {
+    /// Get a reference to the inner future.
+    #[inline]
+    pub fn get_ref(&self) -> &F {
+        &self.future
+    }
+
+    /// Get the inner future back.
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(output) = this.future.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        while Pin::new(&mut *this.timer).poll(cx).is_ready() {
+            // The deadline may have been extended since the timer was set.
+            match this.deadline {
+                Some(deadline) if super::now() < deadline.instant() => {
+                    this.timer.set_at(deadline.instant());
+                }
+                _ => return Poll::Ready(Err(Elapsed(()))),
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+/// A point in time that work has to finish by, which can be pushed back.
+///
+/// Clones of a `Deadline` share the same point in time, so one part of a program can
+/// extend the deadline while another part waits on it. This is useful for timeouts that
+/// reset whenever there is activity, such as idle connections.
+///
+/// ## Example
+///
+/// ```no_run
+/// use keter_reactor::time::Deadline;
+/// use std::time::Duration;
+///
+/// # async fn f() {
+/// let deadline = Deadline::after(Duration::from_secs(30));
+/// let work = async {
+///     // Every message received buys another thirty seconds.
+///     deadline.extend_to_after(Duration::from_secs(30));
+/// };
+/// let _ = deadline.timeout(work).await;
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Deadline(Arc<Mutex<Instant>>);
+
+impl fmt::Debug for Deadline {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Deadline").field(&self.instant()).finish()
+    }
+}
+
+impl Deadline {
+    /// Create a deadline `duration` from now.
+    #[inline]
+    pub fn after(duration: Duration) -> Self {
+        let now = super::now();
+        Self::at(now.checked_add(duration).unwrap_or(now))
+    }
+
+    /// Create a deadline at a specific point in time.
+    #[inline]
+    pub fn at(deadline: Instant) -> Self {
+        Self(Arc::new(Mutex::new(deadline)))
+    }
+
+    /// Get the current point in time of this deadline.
+    #[inline]
+    pub fn instant(&self) -> Instant {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Get how much time is left before the deadline.
+    #[inline]
+    pub fn remaining(&self) -> Duration {
+        self.instant().saturating_duration_since(super::now())
+    }
+
+    /// Tell whether the deadline has passed.
+    #[inline]
+    pub fn is_elapsed(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Push the deadline back by `duration`.
+    #[inline]
+    pub fn extend(&self, duration: Duration) {
+        let mut deadline = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(extended) = deadline.checked_add(duration) {
+            *deadline = extended;
+        }
+    }
+
+    /// Push the deadline back to `deadline`.
+    ///
+    /// Does nothing if `deadline` is earlier than the current deadline.
+    #[inline]
+    pub fn extend_to(&self, deadline: Instant) {
+        let mut current = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        *current = (*current).max(deadline);
+    }
+
+    /// Push the deadline back to `duration` from now.
+    ///
+    /// Does nothing if that is earlier than the current deadline.
+    #[inline]
+    pub fn extend_to_after(&self, duration: Duration) {
+        if let Some(deadline) = super::now().checked_add(duration) {
+            self.extend_to(deadline);
+        }
+    }
+
+    /// Run a future, giving up if it is still running once this deadline passes.
+    ///
+    /// Extending the deadline while the future runs gives it more time.
+    #[inline]
+    pub fn timeout<F: Future>(&self, future: F) -> Timeout<F> {
+        Timeout {
+            future,
+            timer: Timer::at(self.instant()),
+            deadline: Some(self.clone()),
+        }
+    }
+}
+
+#[cfg(all(test, unix, not(target_vendor = "apple"), not(target_os = "android")))]
+mod tests {
+    use super::*;
+    use crate::test_reactor;
+    use crate::time::{self, VirtualClock};
+
+    use futures_lite::future;
+
+    #[test]
+    fn timeouts() {
+        let reactor = test_reactor(|builder| {
+            builder.virtual_clock(VirtualClock::new().with_auto_advance(true))
+        });
+
+        reactor
+            .__block_on_result(async {
+                let ms = Duration::from_millis;
+                let start = time::now();
+
+                // Timeouts give up at the deadline.
+                let slow = timeout(ms(10), Timer::after(ms(20)));
+                assert!(slow.await.is_err());
+                assert_eq!(time::now(), start + ms(10));
+                let fast = timeout_at(start + ms(30), async { 5 });
+                assert_eq!(fast.await, Ok(5));
+                let err =
+                    io::Error::from(timeout(ms(1), future::pending::<()>()).await.unwrap_err());
+                assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+                // Deadlines can be pushed back while they are being waited on.
+                let start = time::now();
+                let deadline = Deadline::after(ms(10));
+                let work = async {
+                    for _ in 0..3 {
+                        Timer::after(ms(8)).await;
+                        deadline.extend_to_after(ms(10));
+                    }
+                };
+                assert!(deadline.timeout(work).await.is_ok());
+                assert_eq!(deadline.instant(), start + ms(34));
+                assert!(deadline.timeout(future::pending::<()>()).await.is_err());
+                assert_eq!(time::now(), start + ms(34));
+                assert!(deadline.is_elapsed());
+                Ok(())
+            })
+            .unwrap();
+    }
+}
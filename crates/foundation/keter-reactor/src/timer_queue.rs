@@ -0,0 +1,293 @@
+// MIT/Apache2 License
+
+//! A queue of timers, for backends that have to keep track of timers themselves.
+
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+use web_time::{Duration, Instant};
+
+/// Registered timers, ordered by deadline.
+pub(crate) struct TimerQueue {
+    /// The inner state of the queue.
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    /// Registered timers, ordered by deadline and then by ID.
+    timers: BTreeMap<(Instant, usize), Waker>,
+
+    /// The ID to use for the next timer.
+    next_id: usize,
+}
+
+impl TimerQueue {
+    /// Create a new, empty timer queue.
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                timers: BTreeMap::new(),
+                next_id: 0,
+            }),
+        }
+    }
+
+    /// Register a timer, returning its ID.
+    #[inline]
+    pub(crate) fn insert(&self, when: Instant, waker: &Waker) -> usize {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let id = inner.next_id;
+        inner.next_id = inner.next_id.wrapping_add(1);
+        inner.timers.insert((when, id), waker.clone());
+        id
+    }
+
+    /// Remove a timer's registration.
+    #[inline]
+    pub(crate) fn remove(&self, when: Instant, id: usize) {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .timers
+            .remove(&(when, id));
+    }
+
+    /// Get the deadline of the next timer to fire, or `None` if there are no timers.
+    #[inline]
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.timers.keys().next().map(|(when, _)| *when)
+    }
+
+    /// Remove all timers that have fired by `now`, returning their wakers.
+    ///
+    /// The wakers should be woken outside of any locks, since waking them may re-register
+    /// timers.
+    pub(crate) fn take_due(&self, now: Instant) -> Vec<Waker> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Split off the timers that have not fired yet.
+        let pending = inner.timers.split_off(&(now, usize::MAX));
+        std::mem::replace(&mut inner.timers, pending)
+            .into_values()
+            .collect()
+    }
+}
+
+/// A timer registered in a [`TimerQueue`].
+pub(crate) struct QueuedTimer<Q: Deref<Target = TimerQueue>> {
+    /// The queue this timer is registered in.
+    queue: Q,
+
+    /// The ID and waker of the timer's registration in the queue.
+    id_and_waker: Option<(usize, Waker)>,
+
+    /// The next time this timer fires.
+    when: Option<Instant>,
+
+    /// The period of the timer, or `Duration::MAX` if it only fires once.
+    period: Duration,
+}
+
+impl<Q: Deref<Target = TimerQueue>> QueuedTimer<Q> {
+    /// Create a timer that never fires.
+    #[inline]
+    pub(crate) fn new(queue: Q) -> Self {
+        Self {
+            queue,
+            id_and_waker: None,
+            when: None,
+            period: Duration::MAX,
+        }
+    }
+
+    /// Get the queue this timer is registered in.
+    #[inline]
+    pub(crate) fn queue(&self) -> &Q {
+        &self.queue
+    }
+
+    /// Get the next time this timer fires.
+    #[inline]
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.when
+    }
+
+    /// Get the period of this timer, if it fires repeatedly.
+    #[inline]
+    pub(crate) fn period(&self) -> Option<Duration> {
+        (self.period != Duration::MAX).then_some(self.period)
+    }
+
+    /// Set this timer to never fire.
+    #[inline]
+    pub(crate) fn set_never(&mut self) {
+        self.clear();
+        self.id_and_waker = None;
+        self.when = None;
+    }
+
+    /// Set this timer to fire at `at`, and then every `period` after that.
+    #[inline]
+    pub(crate) fn set_interval(&mut self, at: Instant, period: Duration) {
+        self.clear();
+        self.when = Some(at);
+        self.period = period;
+
+        // Re-register the timer with its new deadline.
+        if let Some((id, waker)) = self.id_and_waker.as_mut() {
+            *id = self.queue.insert(at, waker);
+        }
+    }
+
+    /// Wait for the next time this timer fires, given the current time.
+    pub(crate) fn poll(&mut self, now: Instant, cx: &mut Context<'_>) -> Poll<()> {
+        match self.when {
+            Some(when) if now >= when => {
+                self.fire(cx);
+                Poll::Ready(())
+            }
+            _ => {
+                self.register(cx);
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Mark the current deadline as passed, and register the next tick, if there is one.
+    pub(crate) fn fire(&mut self, cx: &mut Context<'_>) {
+        let Some(when) = self.when else {
+            return;
+        };
+
+        // The timer has fired; remove its registration.
+        if let Some((id, _)) = self.id_and_waker.take() {
+            self.queue.remove(when, id);
+        }
+
+        // Schedule the next tick, if there is one.
+        self.when = when.checked_add(self.period);
+        if let Some(next) = self.when {
+            let id = self.queue.insert(next, cx.waker());
+            self.id_and_waker = Some((id, cx.waker().clone()));
+        }
+    }
+
+    /// Make sure the current deadline is registered in the queue with the given waker.
+    pub(crate) fn register(&mut self, cx: &mut Context<'_>) {
+        let Some(when) = self.when else {
+            return;
+        };
+
+        match &self.id_and_waker {
+            None => {
+                let id = self.queue.insert(when, cx.waker());
+                self.id_and_waker = Some((id, cx.waker().clone()));
+            }
+            Some((id, waker)) if !waker.will_wake(cx.waker()) => {
+                // Replace the old waker.
+                self.queue.remove(when, *id);
+                let id = self.queue.insert(when, cx.waker());
+                self.id_and_waker = Some((id, cx.waker().clone()));
+            }
+            Some(_) => {}
+        }
+    }
+
+    /// Remove the timer's registration from the queue, if any.
+    #[inline]
+    fn clear(&mut self) {
+        if let (Some(when), Some((id, _))) = (self.when, self.id_and_waker.as_ref()) {
+            self.queue.remove(when, *id);
+        }
+    }
+}
+
+impl<Q: Deref<Target = TimerQueue>> Drop for QueuedTimer<Q> {
+    #[inline]
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer_queue() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::task::Wake;
+
+        struct Counter(AtomicUsize);
+
+        impl Wake for Counter {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let counter = Arc::new(Counter(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let queue = TimerQueue::new();
+        let start = Instant::now();
+        let period = Duration::from_millis(10);
+
+        // A timer that never fires is not registered.
+        let mut timer = QueuedTimer::new(&queue);
+        assert_eq!(timer.poll(start, &mut cx), Poll::Pending);
+        assert_eq!(queue.next_deadline(), None);
+
+        // Polling an interval registers its deadline.
+        timer.set_interval(start + period, period);
+        assert_eq!(timer.poll(start, &mut cx), Poll::Pending);
+        assert_eq!(queue.next_deadline(), Some(start + period));
+        assert!(queue.take_due(start).is_empty());
+
+        // Due timers are woken and then fire, scheduling the next tick.
+        for waker in queue.take_due(start + period) {
+            waker.wake();
+        }
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        assert_eq!(queue.next_deadline(), None);
+        assert_eq!(timer.poll(start + period, &mut cx), Poll::Ready(()));
+        assert_eq!(queue.next_deadline(), Some(start + period * 2));
+
+        // Timers are ordered by deadline.
+        let mut other = QueuedTimer::new(&queue);
+        other.set_interval(start + Duration::from_millis(5), Duration::MAX);
+        assert_eq!(other.poll(start, &mut cx), Poll::Pending);
+        assert_eq!(
+            queue.next_deadline(),
+            Some(start + Duration::from_millis(5))
+        );
+
+        // One-shot timers do not re-register once they fire.
+        assert_eq!(other.poll(start + period, &mut cx), Poll::Ready(()));
+        assert_eq!(other.poll(start + period, &mut cx), Poll::Pending);
+        assert_eq!(queue.next_deadline(), Some(start + period * 2));
+
+        // Resetting a timer moves its registration.
+        timer.set_interval(start + period * 5, period);
+        assert_eq!(queue.next_deadline(), Some(start + period * 5));
+
+        // Timers set to never fire are removed from the queue.
+        timer.set_never();
+        assert_eq!(queue.next_deadline(), None);
+        assert_eq!(timer.poll(start + period * 10, &mut cx), Poll::Pending);
+
+        // Dropping a timer removes it from the queue.
+        timer.set_interval(start + period * 5, period);
+        assert_eq!(timer.poll(start, &mut cx), Poll::Pending);
+        assert_eq!(queue.next_deadline(), Some(start + period * 5));
+        drop(timer);
+        assert_eq!(queue.next_deadline(), None);
+        assert!(queue.take_due(start + period * 10).is_empty());
+    }
+}
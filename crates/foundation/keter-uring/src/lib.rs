@@ -0,0 +1,302 @@
+// MIT/Apache2 License
+
+//! A small, safe io_uring driver for `keter-reactor`.
+//!
+//! Only operations that do not lend any memory to the kernel are exposed: polling file
+//! descriptors for readiness, removing those polls, and waking up the thread waiting on
+//! the ring. None of them can outlive a buffer, which is what makes a safe API possible.
+//! The `unsafe` blocks here only uphold the rules for sharing one ring between threads.
+//!
+//! This crate is empty on platforms other than Linux.
+
+#![cfg(target_os = "linux")]
+
+use io_uring::{opcode, squeue, types, IoUring};
+use rustix::event::PollFlags;
+use rustix::io::Errno;
+
+use std::fmt;
+use std::io;
+use std::os::unix::io::{AsRawFd, BorrowedFd};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// The `user_data` of the no-ops submitted by [`Ring::notify`].
+const NOTIFY: u64 = u64::MAX;
+
+/// The `user_data` of the removals submitted by [`Ring::cancel`].
+const CANCEL: u64 = u64::MAX - 1;
+
+/// The largest `user_data` that can be passed to [`Ring::poll`].
+///
+/// Larger values are used by the ring itself.
+pub const MAX_USER_DATA: u64 = u64::MAX - 2;
+
+/// An io_uring instance that can be shared between threads.
+///
+/// Any thread can queue work on the ring, but only one thread should wait on it.
+pub struct Ring {
+    /// The ring itself.
+    ring: IoUring,
+
+    /// Held while the submission queue is being written to.
+    submission: Mutex<()>,
+
+    /// Held while the completion queue is being read from.
+    completion: Mutex<()>,
+}
+
+impl fmt::Debug for Ring {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ring")
+            .field("entries", &self.ring.params().sq_entries())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The readiness to poll a file descriptor for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interest {
+    /// Wait for the file descriptor to be readable.
+    Readable,
+
+    /// Wait for the file descriptor to be writable.
+    Writable,
+}
+
+/// A finished poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Completion {
+    /// The `user_data` that the poll was submitted with.
+    user_data: u64,
+
+    /// The events that were ready, or a negated error code.
+    result: i32,
+}
+
+impl Completion {
+    /// Get the `user_data` that the poll was submitted with.
+    #[inline]
+    pub fn user_data(&self) -> u64 {
+        self.user_data
+    }
+
+    /// Get whether the poll succeeded.
+    ///
+    /// Polls that were removed through [`Ring::cancel`] fail with `ECANCELED`.
+    #[inline]
+    pub fn result(&self) -> io::Result<()> {
+        match self.result {
+            0.. => Ok(()),
+            errno => Err(io::Error::from_raw_os_error(-errno)),
+        }
+    }
+}
+
+impl Ring {
+    /// Create a new ring with room for `entries` submissions at a time.
+    ///
+    /// This fails if io_uring is not available, either because the kernel is too old or
+    /// because it has been disabled. Linux 5.11 or later is needed to wait with a timeout.
+    pub fn new(entries: u32) -> io::Result<Self> {
+        let ring = IoUring::new(entries)?;
+        if !ring.params().is_feature_ext_arg() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "io_uring cannot wait with a timeout on this kernel",
+            ));
+        }
+
+        Ok(Self {
+            ring,
+            submission: Mutex::new(()),
+            completion: Mutex::new(()),
+        })
+    }
+
+    /// Queue a one-shot poll of `fd`.
+    ///
+    /// Once `fd` is ready, a [`Completion`] with `user_data` is returned from
+    /// [`Ring::wait`]. The poll is handed to the kernel by the next call to
+    /// [`Ring::submit`] or [`Ring::wait`], so `fd` must stay open until then. After that
+    /// the kernel holds its own reference to the file, and the poll keeps going until it
+    /// completes or is removed through [`Ring::cancel`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `user_data` is larger than [`MAX_USER_DATA`].
+    pub fn poll(&self, fd: BorrowedFd<'_>, interest: Interest, user_data: u64) -> io::Result<()> {
+        assert!(user_data <= MAX_USER_DATA, "`user_data` is reserved");
+
+        let flags = match interest {
+            Interest::Readable => PollFlags::IN,
+            Interest::Writable => PollFlags::OUT,
+        };
+        let entry = opcode::PollAdd::new(types::Fd(fd.as_raw_fd()), flags.bits().into())
+            .build()
+            .user_data(user_data);
+        self.push(entry)
+    }
+
+    /// Queue the removal of the poll submitted with `user_data`.
+    ///
+    /// If the poll has not completed yet, it completes with `ECANCELED`.
+    pub fn cancel(&self, user_data: u64) -> io::Result<()> {
+        let entry = opcode::PollRemove::new(user_data).build().user_data(CANCEL);
+        self.push(entry)
+    }
+
+    /// Wake up the thread waiting in [`Ring::wait`].
+    ///
+    /// If no thread is waiting, the next call to [`Ring::wait`] returns right away.
+    pub fn notify(&self) -> io::Result<()> {
+        self.push(opcode::Nop::new().build().user_data(NOTIFY))?;
+        self.submit()
+    }
+
+    /// Hand every queued operation to the kernel.
+    #[inline]
+    pub fn submit(&self) -> io::Result<()> {
+        self.ring.submit().map(drop)
+    }
+
+    /// Hand every queued operation to the kernel and wait for polls to complete.
+    ///
+    /// This waits until at least one operation completes, [`Ring::notify`] is called or
+    /// `timeout` passes. Finished polls are appended to `completions`. This is a single
+    /// system call, unless the submission queue had to be flushed early.
+    pub fn wait(
+        &self,
+        timeout: Option<Duration>,
+        completions: &mut Vec<Completion>,
+    ) -> io::Result<()> {
+        let submitter = self.ring.submitter();
+        let result = match timeout {
+            Some(timeout) if timeout.is_zero() => submitter.submit(),
+            Some(timeout) => {
+                let timeout = types::Timespec::from(timeout);
+                let args = types::SubmitArgs::new().timespec(&timeout);
+                submitter.submit_with_args(1, &args)
+            }
+            None => submitter.submit_and_wait(1),
+        };
+
+        match result.map_err(|err| Errno::from_io_error(&err).ok_or(err)) {
+            // Running out of time or being interrupted still leaves completions to read.
+            Ok(_) | Err(Ok(Errno::TIME | Errno::INTR | Errno::BUSY)) => {}
+            Err(Ok(errno)) => return Err(errno.into()),
+            Err(Err(err)) => return Err(err),
+        }
+
+        let _guard = lock(&self.completion);
+
+        // SAFETY: `completion` is held, so this is the only completion queue.
+        let queue = unsafe { self.ring.completion_shared() };
+        completions.extend(
+            queue
+                .filter(|entry| entry.user_data() <= MAX_USER_DATA)
+                .map(|entry| Completion {
+                    user_data: entry.user_data(),
+                    result: entry.result(),
+                }),
+        );
+
+        Ok(())
+    }
+
+    /// Add an entry to the submission queue, making room for it if needed.
+    fn push(&self, entry: squeue::Entry) -> io::Result<()> {
+        let _guard = lock(&self.submission);
+
+        loop {
+            // SAFETY: `submission` is held, so this is the only submission queue.
+            let mut queue = unsafe { self.ring.submission_shared() };
+
+            // SAFETY: none of the entries built here refer to any memory.
+            if unsafe { queue.push(&entry) }.is_ok() {
+                return Ok(());
+            }
+
+            // The queue is full, so hand its entries to the kernel to make room.
+            drop(queue);
+            self.submit()?;
+        }
+    }
+}
+
+/// Lock a mutex, ignoring poison.
+#[inline]
+fn lock(mutex: &Mutex<()>) -> MutexGuard<'_, ()> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+    use std::os::unix::io::AsFd;
+    use std::os::unix::net::UnixStream;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    #[test]
+    fn poll() {
+        let ring = Ring::new(4).unwrap();
+        let (left, mut right) = UnixStream::pair().unwrap();
+        let mut completions = Vec::new();
+
+        // Sockets with room in their buffers are writable right away.
+        ring.poll(left.as_fd(), Interest::Writable, 1).unwrap();
+        ring.poll(left.as_fd(), Interest::Readable, 2).unwrap();
+        ring.wait(None, &mut completions).unwrap();
+        assert_eq!(completions.len(), 1);
+        assert_eq!(completions[0].user_data(), 1);
+        assert!(completions[0].result().is_ok());
+
+        // Nothing is readable yet, so the wait times out.
+        completions.clear();
+        let start = Instant::now();
+        ring.wait(Some(Duration::from_millis(20)), &mut completions)
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(completions.is_empty());
+
+        // Polls complete once the file descriptor is ready.
+        right.write_all(&[1]).unwrap();
+        ring.wait(None, &mut completions).unwrap();
+        assert_eq!(completions[0].user_data(), 2);
+
+        // Removed polls complete with an error.
+        completions.clear();
+        ring.poll(right.as_fd(), Interest::Readable, 3).unwrap();
+        ring.cancel(3).unwrap();
+        while completions.is_empty() {
+            ring.wait(None, &mut completions).unwrap();
+        }
+        assert_eq!(completions[0].user_data(), 3);
+        assert_eq!(
+            completions[0].result().unwrap_err().raw_os_error(),
+            Some(Errno::CANCELED.raw_os_error())
+        );
+    }
+
+    #[test]
+    fn notify() {
+        let ring = Arc::new(Ring::new(4).unwrap());
+        let waker = std::thread::spawn({
+            let ring = ring.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(20));
+                ring.notify().unwrap();
+            }
+        });
+
+        // Notifications wake the ring without producing completions.
+        let mut completions = Vec::new();
+        ring.wait(None, &mut completions).unwrap();
+        assert!(completions.is_empty());
+        waker.join().unwrap();
+    }
+}
@@ -230,7 +230,7 @@ impl Environment for AndroidEnvironment {
         pwd: Option<&OsStr>,
     ) -> Result<Self::Command> {
         assert!(pwd.is_none());
-        let is_cargo = cmd.to_str().map_or(false, |s| s.ends_with("cargo"));
+        let is_cargo = cmd.to_str().is_some_and(|s| s.ends_with("cargo"));
 
         // For `cargo test --tests` and `cargo test --doc`, we can't actually run these on Android.
         // Just skip them for now.
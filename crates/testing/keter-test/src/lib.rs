@@ -200,7 +200,7 @@ pub fn run_tests<T>(f: impl FnOnce(&TestHarness) -> T) -> T {
     };
 
     // Run the tests.
-    let value = f(&harness);
+    f(&harness);
 
     // Count tests.
     let TestHarness { reporter, count } = harness;
@@ -211,9 +211,7 @@ pub fn run_tests<T>(f: impl FnOnce(&TestHarness) -> T) -> T {
 
     // Finish with an exit code.
     let code = reporter.finish();
-    std::process::exit(code);
-
-    value
+    std::process::exit(code)
 }
 
 /// Drive a TCP listener at the specified port.
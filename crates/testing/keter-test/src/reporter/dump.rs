@@ -8,6 +8,7 @@
 use super::Reporter;
 
 /// Report tests to the output console in JSON form.
+#[derive(Default)]
 pub struct DumpReporter;
 
 impl DumpReporter {